@@ -0,0 +1,338 @@
+use super::super::mapper::Mapper;
+use super::super::region::Region;
+use super::channel::{Channel, ChannelControl, CHANNELS};
+use super::dmc::Dmc;
+use super::filter::{FilterChain, OutputFilter};
+use super::frame_counter::{FrameClock, FrameCounter};
+use super::mixer::mix;
+use super::noise::Noise;
+use super::pulse::Pulse;
+use super::resampler::Resampler;
+use super::triangle::Triangle;
+use super::vgm::VgmLogger;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+// Separate output stream of a channel
+#[derive(Debug)]
+struct Tap {
+    resampler: Resampler,
+    filters: FilterChain,
+}
+
+#[derive(Debug)]
+pub struct APU {
+    pub pulse_1: Pulse,
+    pub pulse_2: Pulse,
+    pub triangle: Triangle,
+    pub noise: Noise,
+    pub dmc: Dmc,
+    frame_counter: FrameCounter,
+    resampler: Resampler,
+    sample_rate: u32,
+    output_filter: OutputFilter,
+    filters: FilterChain,
+    controls: [ChannelControl; CHANNELS],
+    gains: [f32; CHANNELS], // effective gains with mute and solo applied
+    taps: Vec<Tap>,
+    registers: [u8; 0x18], // last values written to $4000-$4017
+    vgm: Option<VgmLogger>,
+    region: Region,
+    cycle: usize, // CPU cycles since power on
+}
+
+impl APU {
+    pub fn new(mapper: Rc<RefCell<Box<dyn Mapper>>>) -> APU {
+        APU {
+            pulse_1: Pulse::new(1),
+            pulse_2: Pulse::new(2),
+            triangle: Triangle::default(),
+            noise: Noise::new(),
+            dmc: Dmc::new(mapper),
+            frame_counter: FrameCounter::new(),
+            resampler: Resampler::new(
+                Region::default().cpu_frequency() as f64,
+                DEFAULT_SAMPLE_RATE as f64,
+            ),
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            output_filter: OutputFilter::default(),
+            filters: FilterChain::new(OutputFilter::default(), DEFAULT_SAMPLE_RATE),
+            controls: [ChannelControl::default(); CHANNELS],
+            gains: [1.0; CHANNELS],
+            taps: Vec::new(),
+            registers: [0; 0x18],
+            vgm: None,
+            region: Region::default(),
+            cycle: 0,
+        }
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.noise.set_region(region);
+        self.dmc.set_region(region);
+        self.frame_counter.set_region(region);
+        self.update_rates();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.update_rates();
+    }
+
+    pub fn get_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn set_output_filter(&mut self, output_filter: OutputFilter) {
+        self.output_filter = output_filter;
+        self.update_rates();
+    }
+
+    pub fn get_output_filter(&self) -> OutputFilter {
+        self.output_filter
+    }
+
+    fn update_rates(&mut self) {
+        let clock_rate = self.region.cpu_frequency() as f64;
+        self.resampler
+            .set_rates(clock_rate, self.sample_rate as f64);
+        self.filters = FilterChain::new(self.output_filter, self.sample_rate);
+        for tap in &mut self.taps {
+            tap.resampler.set_rates(clock_rate, self.sample_rate as f64);
+            tap.filters = FilterChain::new(self.output_filter, self.sample_rate);
+        }
+    }
+
+    pub fn set_channel_gain(&mut self, channel: Channel, gain: f32) {
+        self.controls[channel as usize].gain = gain;
+        self.update_gains();
+    }
+
+    pub fn get_channel_gain(&self, channel: Channel) -> f32 {
+        self.controls[channel as usize].gain
+    }
+
+    pub fn set_channel_muted(&mut self, channel: Channel, muted: bool) {
+        self.controls[channel as usize].muted = muted;
+        self.update_gains();
+    }
+
+    // While any channel is soloed the others are silent
+    pub fn set_channel_solo(&mut self, channel: Channel, solo: bool) {
+        self.controls[channel as usize].solo = solo;
+        self.update_gains();
+    }
+
+    fn update_gains(&mut self) {
+        let solo = self.controls.iter().any(|c| c.solo);
+        for (gain, control) in self.gains.iter_mut().zip(self.controls.iter()) {
+            *gain = if control.muted || (solo && !control.solo) {
+                0.0
+            } else {
+                control.gain
+            };
+        }
+    }
+
+    // Taps produce every channel on its own at output rate, regardless
+    // of mixing settings. Disabled by default as resampling is not free.
+    pub fn set_channel_taps(&mut self, enabled: bool) {
+        if !enabled {
+            self.taps.clear();
+        } else if self.taps.is_empty() {
+            let clock_rate = self.region.cpu_frequency() as f64;
+            for _ in 0..CHANNELS {
+                self.taps.push(Tap {
+                    resampler: Resampler::new(clock_rate, self.sample_rate as f64),
+                    filters: FilterChain::new(self.output_filter, self.sample_rate),
+                });
+            }
+        }
+    }
+
+    pub fn channel_taps_enabled(&self) -> bool {
+        !self.taps.is_empty()
+    }
+
+    // Reading status acknowledges frame IRQ
+    pub fn read_register(&mut self, address: u16) -> u8 {
+        match address {
+            0x4015 => {
+                let mut value = 0;
+                if self.pulse_1.length.active() {
+                    value |= 0x01;
+                }
+                if self.pulse_2.length.active() {
+                    value |= 0x02;
+                }
+                if self.triangle.length.active() {
+                    value |= 0x04;
+                }
+                if self.noise.length.active() {
+                    value |= 0x08;
+                }
+                if self.dmc.active() {
+                    value |= 0x10;
+                }
+                if self.frame_counter.irq {
+                    value |= 0x40;
+                }
+                if self.dmc.irq {
+                    value |= 0x80;
+                }
+                self.frame_counter.irq = false;
+                value
+            }
+            _ => 0,
+        }
+    }
+
+    pub fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x4000..0x4004 => self.pulse_1.write(address - 0x4000, value),
+            0x4004..0x4008 => self.pulse_2.write(address - 0x4004, value),
+            0x4008..0x400C => self.triangle.write(address - 0x4008, value),
+            0x400C..0x4010 => self.noise.write(address - 0x400C, value),
+            0x4010..=0x4013 => self.dmc.write(address - 0x4010, value),
+            0x4015 => {
+                self.pulse_1.length.set_enabled(value & 0x01 != 0);
+                self.pulse_2.length.set_enabled(value & 0x02 != 0);
+                self.triangle.length.set_enabled(value & 0x04 != 0);
+                self.noise.length.set_enabled(value & 0x08 != 0);
+                self.dmc.set_enabled(value & 0x10 != 0);
+            }
+            0x4017 => self.frame_counter.write(value, self.cycle % 2 == 1),
+            _ => {}
+        }
+
+        if let 0x4000..=0x4017 = address {
+            self.registers[address as usize - 0x4000] = value;
+            self.log_write(address, value);
+        }
+    }
+
+    fn log_write(&mut self, address: u16, value: u8) {
+        if let Some(vgm) = &mut self.vgm {
+            let cycle = self.cycle as u64;
+            // Sample memory goes along with writes that may start playback
+            if address == 0x4013 || (address == 0x4015 && value & 0x10 != 0) {
+                let (sample_address, data) = self.dmc.sample_data();
+                vgm.sample(cycle, sample_address, &data);
+            }
+            vgm.write(cycle, address, value);
+        }
+    }
+
+    // Starts recording register writes, current register values are
+    // written first so the log doesn't depend on earlier state
+    pub fn start_vgm_log(&mut self) {
+        let clock = self.region.cpu_frequency() as u64;
+        self.vgm = Some(VgmLogger::new(clock, self.cycle as u64));
+
+        let registers = self.registers;
+        for address in (0x4000..0x4014).chain(vec![0x4015, 0x4017]) {
+            self.log_write(address, registers[address as usize - 0x4000]);
+        }
+    }
+
+    // Returns VGM file with writes since start_vgm_log
+    pub fn stop_vgm_log(&mut self) -> Option<Vec<u8>> {
+        let frame_rate = if self.region == Region::Ntsc { 60 } else { 50 };
+        let cycle = self.cycle as u64;
+        self.vgm.take().map(|vgm| vgm.finish(cycle, frame_rate))
+    }
+
+    // Called once per CPU cycle
+    pub fn step(&mut self) {
+        // Pulse timers are clocked every other CPU cycle
+        if self.cycle % 2 == 1 {
+            self.pulse_1.step();
+            self.pulse_2.step();
+        }
+        self.triangle.step();
+        self.noise.step();
+        self.dmc.step();
+        self.cycle += 1;
+
+        match self.frame_counter.step() {
+            FrameClock::Quarter => self.clock_quarter_frame(),
+            FrameClock::Half => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            }
+            FrameClock::None => {}
+        }
+
+        let levels = [
+            self.pulse_1.output() as f32,
+            self.pulse_2.output() as f32,
+            self.triangle.output() as f32,
+            self.noise.output() as f32,
+            self.dmc.output() as f32,
+        ];
+        let mut mixed = levels;
+        for (level, gain) in mixed.iter_mut().zip(self.gains.iter()) {
+            *level *= gain;
+        }
+        self.resampler.add(mix(&mixed));
+        self.resampler.clock();
+
+        for (i, tap) in self.taps.iter_mut().enumerate() {
+            let mut single = [0.0; CHANNELS];
+            single[i] = levels[i];
+            tap.resampler.add(mix(&single));
+            tap.resampler.clock();
+        }
+    }
+
+    // Moves samples generated so far at output rate to the buffer
+    pub fn read_samples(&mut self, samples: &mut Vec<f32>) {
+        let start = samples.len();
+        self.resampler.read_samples(samples);
+        for sample in &mut samples[start..] {
+            *sample = self.filters.process(*sample);
+        }
+    }
+
+    // Same as read_samples for a single channel, taps must be enabled
+    pub fn read_channel_samples(&mut self, channel: Channel, samples: &mut Vec<f32>) {
+        if let Some(tap) = self.taps.get_mut(channel as usize) {
+            let start = samples.len();
+            tap.resampler.read_samples(samples);
+            for sample in &mut samples[start..] {
+                *sample = tap.filters.process(*sample);
+            }
+        }
+    }
+
+    // IRQ output, level triggered
+    pub fn irq(&self) -> bool {
+        self.frame_irq() || self.dmc.irq
+    }
+
+    pub fn frame_irq(&self) -> bool {
+        self.frame_counter.irq
+    }
+
+    // CPU cycles stolen by DMC sample fetches since last call
+    pub fn take_dma_requests(&mut self) -> usize {
+        self.dmc.take_dma_requests()
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse_1.clock_quarter_frame();
+        self.pulse_2.clock_quarter_frame();
+        self.triangle.clock_quarter_frame();
+        self.noise.clock_quarter_frame();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse_1.clock_half_frame();
+        self.pulse_2.clock_half_frame();
+        self.triangle.clock_half_frame();
+        self.noise.clock_half_frame();
+    }
+}
@@ -0,0 +1,41 @@
+pub const CHANNELS: usize = 5;
+
+/// APU sound channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+}
+
+impl Channel {
+    pub fn all() -> [Channel; CHANNELS] {
+        [
+            Channel::Pulse1,
+            Channel::Pulse2,
+            Channel::Triangle,
+            Channel::Noise,
+            Channel::Dmc,
+        ]
+    }
+}
+
+// Mixing settings of a single channel
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelControl {
+    pub gain: f32,
+    pub muted: bool,
+    pub solo: bool,
+}
+
+impl Default for ChannelControl {
+    fn default() -> Self {
+        ChannelControl {
+            gain: 1.0,
+            muted: false,
+            solo: false,
+        }
+    }
+}
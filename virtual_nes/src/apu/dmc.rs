@@ -0,0 +1,172 @@
+use super::super::mapper::Mapper;
+use super::super::region::Region;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// Delta modulation channel playing 1-bit samples from $C000-$FFFF
+#[derive(Debug)]
+pub struct Dmc {
+    mapper: Rc<RefCell<Box<dyn Mapper>>>,
+    pub irq: bool,
+    irq_enabled: bool,
+    looping: bool,
+    timer: u16,
+    period: usize,
+    periods: [u16; 16],
+    level: u8, // 7-bit output level
+    sample_address: u16,
+    sample_length: u16,
+    address: u16,
+    bytes_remaining: u16,
+    buffer: Option<u8>,
+    shift: u8,
+    bits_remaining: u8,
+    silence: bool,
+    dma_requests: usize,
+}
+
+impl Dmc {
+    pub fn new(mapper: Rc<RefCell<Box<dyn Mapper>>>) -> Dmc {
+        Dmc {
+            mapper: mapper,
+            irq: false,
+            irq_enabled: false,
+            looping: false,
+            timer: 0,
+            period: 0,
+            periods: Region::default().dmc_periods(),
+            level: 0,
+            sample_address: 0xC000,
+            sample_length: 1,
+            address: 0xC000,
+            bytes_remaining: 0,
+            buffer: None,
+            shift: 0,
+            bits_remaining: 8,
+            silence: true,
+            dma_requests: 0,
+        }
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.periods = region.dmc_periods();
+    }
+
+    // Register is 0-3 for $4010-$4013
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.irq_enabled = value & 0x80 != 0;
+                self.looping = value & 0x40 != 0;
+                self.period = value as usize & 0x0F;
+                if !self.irq_enabled {
+                    self.irq = false;
+                }
+            }
+            1 => self.level = value & 0x7F,
+            2 => self.sample_address = 0xC000 | ((value as u16) << 6),
+            3 => self.sample_length = ((value as u16) << 4) | 1,
+            _ => {}
+        }
+    }
+
+    // Controlled by $4015, enabling restarts a finished sample
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+            self.fetch();
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    // Current sample as address and bytes, samples crossing $FFFF are cut
+    pub fn sample_data(&self) -> (u16, Vec<u8>) {
+        let end = (self.sample_address as u32 + self.sample_length as u32).min(0x10000);
+        let mapper = self.mapper.borrow();
+        let data = (self.sample_address as u32..end)
+            .map(|address| mapper.read(address as u16))
+            .collect();
+        (self.sample_address, data)
+    }
+
+    // Sample fetches since last call, each one halts CPU for a DMA
+    pub fn take_dma_requests(&mut self) -> usize {
+        let requests = self.dma_requests;
+        self.dma_requests = 0;
+        requests
+    }
+
+    fn restart(&mut self) {
+        self.address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    // Clocked every CPU cycle
+    pub fn step(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.periods[self.period] - 1;
+            self.clock_output();
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_output(&mut self) {
+        if !self.silence {
+            if self.shift & 1 != 0 {
+                if self.level <= 125 {
+                    self.level += 2;
+                }
+            } else if self.level >= 2 {
+                self.level -= 2;
+            }
+        }
+        self.shift >>= 1;
+        self.bits_remaining -= 1;
+
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.buffer.take() {
+                Some(value) => {
+                    self.shift = value;
+                    self.silence = false;
+                }
+                None => self.silence = true,
+            }
+            self.fetch();
+        }
+    }
+
+    // Refills empty sample buffer, address wraps from $FFFF to $8000
+    fn fetch(&mut self) {
+        if self.buffer.is_some() || self.bytes_remaining == 0 {
+            return;
+        }
+
+        self.dma_requests += 1;
+        self.buffer = Some(self.mapper.borrow().read(self.address));
+        self.address = match self.address {
+            0xFFFF => 0x8000,
+            address => address + 1,
+        };
+        self.bytes_remaining -= 1;
+
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        self.level
+    }
+}
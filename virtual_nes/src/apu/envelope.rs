@@ -0,0 +1,49 @@
+// Volume envelope shared by pulse and noise channels
+#[derive(Debug, Default)]
+pub struct Envelope {
+    start: bool,
+    looping: bool, // same bit as length counter halt
+    constant: bool,
+    volume: u8, // constant volume or divider period
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    pub fn write(&mut self, value: u8) {
+        self.looping = value & 0x20 != 0;
+        self.constant = value & 0x10 != 0;
+        self.volume = value & 0x0F;
+    }
+
+    // Writes to the length counter load register restart the envelope
+    pub fn restart(&mut self) {
+        self.start = true;
+    }
+
+    // Clocked by quarter frames
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+        } else if self.divider == 0 {
+            self.divider = self.volume;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.constant {
+            self.volume
+        } else {
+            self.decay
+        }
+    }
+}
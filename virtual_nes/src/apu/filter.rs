@@ -0,0 +1,89 @@
+use std::f32::consts::PI;
+
+/// Analog filtering applied to the mixed APU output.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFilter {
+    // 90 Hz and 440 Hz high-pass followed by 14 kHz low-pass
+    #[default]
+    Nes,
+    // 37 Hz high-pass and 14 kHz low-pass
+    Famicom,
+    // Unfiltered DAC output with DC offset
+    Raw,
+}
+
+// First-order RC filter running at output sample rate
+#[derive(Debug)]
+enum Filter {
+    HighPass { alpha: f32, input: f32, output: f32 },
+    LowPass { alpha: f32, output: f32 },
+}
+
+impl Filter {
+    fn high_pass(sample_rate: f32, cutoff: f32) -> Filter {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        Filter::HighPass {
+            alpha: rc / (rc + dt),
+            input: 0.0,
+            output: 0.0,
+        }
+    }
+
+    fn low_pass(sample_rate: f32, cutoff: f32) -> Filter {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        Filter::LowPass {
+            alpha: dt / (rc + dt),
+            output: 0.0,
+        }
+    }
+
+    fn process(&mut self, sample: f32) -> f32 {
+        match self {
+            Filter::HighPass {
+                alpha,
+                input,
+                output,
+            } => {
+                *output = *alpha * (*output + sample - *input);
+                *input = sample;
+                *output
+            }
+            Filter::LowPass { alpha, output } => {
+                *output += *alpha * (sample - *output);
+                *output
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FilterChain {
+    filters: Vec<Filter>,
+}
+
+impl FilterChain {
+    pub fn new(mode: OutputFilter, sample_rate: u32) -> FilterChain {
+        let rate = sample_rate as f32;
+        let filters = match mode {
+            OutputFilter::Nes => vec![
+                Filter::high_pass(rate, 90.0),
+                Filter::high_pass(rate, 440.0),
+                Filter::low_pass(rate, 14_000.0),
+            ],
+            OutputFilter::Famicom => vec![
+                Filter::high_pass(rate, 37.0),
+                Filter::low_pass(rate, 14_000.0),
+            ],
+            OutputFilter::Raw => vec![],
+        };
+        FilterChain { filters: filters }
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        self.filters
+            .iter_mut()
+            .fold(sample, |sample, filter| filter.process(sample))
+    }
+}
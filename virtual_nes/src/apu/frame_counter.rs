@@ -0,0 +1,96 @@
+use super::super::region::Region;
+
+#[derive(Debug, PartialEq)]
+pub enum FrameClock {
+    None,
+    Quarter,
+    Half, // half frames are quarter frames too
+}
+
+// Frame sequencer controlled by $4017
+#[derive(Debug)]
+pub struct FrameCounter {
+    steps: [usize; 5],
+    five_step: bool,
+    irq_inhibit: bool,
+    pub irq: bool,
+    cycle: usize, // position in sequence
+    pending: Option<u8>,
+    delay: usize, // CPU cycles until pending write takes effect
+}
+
+impl FrameCounter {
+    pub fn new() -> FrameCounter {
+        FrameCounter {
+            steps: Region::default().frame_counter_steps(),
+            five_step: false,
+            irq_inhibit: false,
+            irq: false,
+            cycle: 0,
+            pending: None,
+            delay: 0,
+        }
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.steps = region.frame_counter_steps();
+    }
+
+    // Inhibit flag clears IRQ right away, but the sequencer is reset
+    // 3 or 4 CPU cycles later depending on the APU cycle parity
+    pub fn write(&mut self, value: u8, odd_cycle: bool) {
+        self.irq_inhibit = value & 0x40 != 0;
+        if self.irq_inhibit {
+            self.irq = false;
+        }
+        self.pending = Some(value);
+        self.delay = if odd_cycle { 4 } else { 3 };
+    }
+
+    // Clocked every CPU cycle
+    pub fn step(&mut self) -> FrameClock {
+        if let Some(value) = self.pending {
+            self.delay -= 1;
+            if self.delay == 0 {
+                self.pending = None;
+                self.five_step = value & 0x80 != 0;
+                self.cycle = 1;
+                // 5-step mode clocks units immediately
+                return if self.five_step {
+                    FrameClock::Half
+                } else {
+                    FrameClock::None
+                };
+            }
+        }
+
+        let last = if self.five_step {
+            self.steps[4]
+        } else {
+            self.steps[3]
+        };
+        // Last cycle of the sequence is also the first of the next one
+        if self.cycle == last + 1 {
+            self.cycle = 0;
+            self.set_irq();
+        }
+
+        let clock = match self.cycle {
+            c if c == self.steps[0] || c == self.steps[2] => FrameClock::Quarter,
+            c if c == self.steps[1] || c == last => FrameClock::Half,
+            _ => FrameClock::None,
+        };
+        // IRQ is raised during the last three cycles of 4-step sequence
+        if self.cycle + 1 >= last {
+            self.set_irq();
+        }
+        self.cycle += 1;
+        clock
+    }
+
+    fn set_irq(&mut self) {
+        if !self.five_step && !self.irq_inhibit {
+            self.irq = true;
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use super::channel::CHANNELS;
+
+// 2A03 nonlinear DAC approximation. Levels are 4-bit channel outputs and
+// 7-bit DMC level in channel order, result is in 0.0-1.0 range.
+pub fn mix(levels: &[f32; CHANNELS]) -> f32 {
+    let [pulse_1, pulse_2, triangle, noise, dmc] = *levels;
+
+    let pulse = pulse_1 + pulse_2;
+    let pulse_out = if pulse == 0.0 {
+        0.0
+    } else {
+        95.88 / (8128.0 / pulse + 100.0)
+    };
+
+    let tnd = triangle / 8227.0 + noise / 12241.0 + dmc / 22638.0;
+    let tnd_out = if tnd == 0.0 {
+        0.0
+    } else {
+        159.79 / (1.0 / tnd + 100.0)
+    };
+
+    pulse_out + tnd_out
+}
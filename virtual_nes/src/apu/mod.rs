@@ -0,0 +1,497 @@
+mod apu;
+mod channel;
+mod dmc;
+mod envelope;
+mod filter;
+mod frame_counter;
+mod length_counter;
+mod mixer;
+mod noise;
+mod pulse;
+mod resampler;
+mod triangle;
+mod vgm;
+
+pub use self::apu::APU;
+pub use self::channel::Channel;
+pub use self::filter::OutputFilter;
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapper::Mapper;
+    use super::super::memory::Memory;
+    use super::super::rom::Mirroring;
+    use super::super::tests::run_test_rom;
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // Cartridge with every PRG byte set to the same value
+    #[derive(Debug)]
+    struct SampleMapper {
+        mirroring: Mirroring,
+        value: u8,
+    }
+
+    impl Memory for SampleMapper {
+        fn read(&self, _address: u16) -> u8 {
+            self.value
+        }
+
+        fn write(&mut self, _address: u16, _value: u8) {}
+    }
+
+    impl Mapper for SampleMapper {
+        fn get_mirroring(&self) -> &Mirroring {
+            &self.mirroring
+        }
+    }
+
+    fn create_apu() -> APU {
+        let mapper: Box<dyn Mapper> = Box::new(SampleMapper {
+            mirroring: Mirroring::Horizontal,
+            value: 0xFF,
+        });
+        APU::new(Rc::new(RefCell::new(mapper)))
+    }
+
+    fn step_cycles(apu: &mut APU, cycles: usize) {
+        for _ in 0..cycles {
+            apu.step();
+        }
+    }
+
+    #[test]
+    fn length_counter() {
+        let mut apu = create_apu();
+
+        // Ignored while channel is disabled
+        apu.write_register(0x4003, 0x08);
+        assert_eq!(apu.read_register(0x4015), 0x00);
+
+        apu.write_register(0x4015, 0x03);
+        apu.write_register(0x4003, 0x08);
+        apu.write_register(0x4007, 0x08);
+        assert_eq!(apu.read_register(0x4015), 0x03);
+
+        apu.write_register(0x4015, 0x01);
+        assert_eq!(apu.read_register(0x4015), 0x01);
+
+        // Index 0 loads 10, counted down twice per 4-step sequence
+        apu.write_register(0x4003, 0x00);
+        step_cycles(&mut apu, 29830 * 4 + 29829);
+        assert_eq!(apu.read_register(0x4015) & 0x1F, 0x01);
+        step_cycles(&mut apu, 1);
+        assert_eq!(apu.read_register(0x4015) & 0x1F, 0x00);
+
+        // Halt flag stops counting
+        apu.write_register(0x4000, 0x20);
+        apu.write_register(0x4003, 0x00);
+        step_cycles(&mut apu, 29830 * 6);
+        assert_eq!(apu.read_register(0x4015) & 0x1F, 0x01);
+    }
+
+    #[test]
+    fn envelope() {
+        let mut apu = create_apu();
+        apu.write_register(0x4015, 0x01);
+
+        apu.write_register(0x4000, 0x1A);
+        assert_eq!(apu.pulse_1.envelope.output(), 0x0A);
+
+        // Decay level restarts at 15 and decreases every quarter frame
+        apu.write_register(0x4000, 0x00);
+        apu.write_register(0x4003, 0x00);
+        step_cycles(&mut apu, 7458);
+        assert_eq!(apu.pulse_1.envelope.output(), 15);
+        step_cycles(&mut apu, 7456);
+        assert_eq!(apu.pulse_1.envelope.output(), 14);
+    }
+
+    #[test]
+    fn sweep() {
+        let mut apu = create_apu();
+        apu.write_register(0x4015, 0x03);
+
+        // Negate with shift 1, 75% duty so output starts high
+        for &base in &[0x4000, 0x4004] {
+            apu.write_register(base, 0xFF);
+            apu.write_register(base + 1, 0x89);
+            apu.write_register(base + 2, 0x00);
+            apu.write_register(base + 3, 0x09);
+        }
+        step_cycles(&mut apu, 14914);
+        assert_eq!(apu.pulse_1.get_period(), 0x7F);
+        assert_eq!(apu.pulse_2.get_period(), 0x80);
+
+        // Target above $7FF mutes the channel even with sweep disabled
+        apu.write_register(0x4001, 0x00);
+        apu.write_register(0x4002, 0xFF);
+        apu.write_register(0x4003, 0x07);
+        assert_eq!(apu.pulse_1.output(), 0);
+        apu.write_register(0x4001, 0x08);
+        assert_eq!(apu.pulse_1.output(), 0x0F);
+    }
+
+    #[test]
+    fn triangle() {
+        let mut apu = create_apu();
+        apu.write_register(0x4015, 0x04);
+        assert_eq!(apu.read_register(0x4015), 0x00);
+
+        apu.write_register(0x4008, 0x02);
+        apu.write_register(0x400A, 0x10);
+        apu.write_register(0x400B, 0x00);
+        assert_eq!(apu.read_register(0x4015), 0x04);
+
+        // Linear counter is loaded on the first quarter frame
+        step_cycles(&mut apu, 1000);
+        assert_eq!(apu.triangle.output(), 15);
+        step_cycles(&mut apu, 7458 - 1000 + 100);
+        assert_ne!(apu.triangle.output(), 15);
+
+        // And silences the channel two quarter frames later
+        step_cycles(&mut apu, 22372 - 7558);
+        let output = apu.triangle.output();
+        step_cycles(&mut apu, 1000);
+        assert_eq!(apu.triangle.output(), output);
+
+        // Ultrasonic period holds the sequencer
+        apu.write_register(0x4008, 0x7F);
+        apu.write_register(0x400A, 0x01);
+        apu.write_register(0x400B, 0x00);
+        step_cycles(&mut apu, 7458);
+        let output = apu.triangle.output();
+        step_cycles(&mut apu, 1000);
+        assert_eq!(apu.triangle.output(), output);
+    }
+
+    fn noise_outputs(short_mode: bool, count: usize) -> Vec<u8> {
+        let mut apu = create_apu();
+        apu.write_register(0x4015, 0x08);
+        apu.write_register(0x400C, 0x3F);
+        apu.write_register(0x400E, if short_mode { 0x80 } else { 0x00 });
+        apu.write_register(0x400F, 0x00);
+        (0..count)
+            .map(|_| {
+                step_cycles(&mut apu, 4);
+                apu.noise.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn noise() {
+        let short = noise_outputs(true, 93 * 2);
+        assert!((0..93).all(|i| short[i] == short[i + 93]));
+        assert!(short.contains(&0) && short.contains(&15));
+
+        let long = noise_outputs(false, 32767 + 93);
+        assert!((0..93).all(|i| long[i] == long[i + 32767]));
+        assert!((0..93).any(|i| long[i] != long[i + 93]));
+    }
+
+    #[test]
+    fn dmc_fetch() {
+        let mut apu = create_apu();
+
+        // One byte sample at the fastest rate
+        apu.write_register(0x4010, 0x0F);
+        apu.write_register(0x4011, 0x00);
+        apu.write_register(0x4013, 0x00);
+        apu.write_register(0x4015, 0x10);
+        assert_eq!(apu.take_dma_requests(), 1);
+        assert_eq!(apu.take_dma_requests(), 0);
+        assert_eq!(apu.read_register(0x4015), 0x00);
+
+        // Output unit is silent until current 8 bits are shifted out
+        step_cycles(&mut apu, 54 * 8);
+        assert_eq!(apu.dmc.output(), 0);
+        step_cycles(&mut apu, 54 * 8);
+        assert_eq!(apu.dmc.output(), 16);
+        assert!(!apu.irq());
+
+        // Looping sample keeps fetching
+        apu.write_register(0x4010, 0x4F);
+        apu.write_register(0x4015, 0x10);
+        step_cycles(&mut apu, 54 * 8 * 10);
+        assert_eq!(apu.read_register(0x4015), 0x10);
+        assert_eq!(apu.take_dma_requests(), 11);
+        assert_eq!(apu.dmc.output(), 126);
+    }
+
+    #[test]
+    fn dmc_irq() {
+        let mut apu = create_apu();
+
+        // One byte sample ends right on the first fetch
+        apu.write_register(0x4010, 0x8F);
+        apu.write_register(0x4013, 0x00);
+        apu.write_register(0x4015, 0x10);
+        assert!(apu.irq());
+        assert_eq!(apu.read_register(0x4015), 0x80);
+
+        // Writing $4015 acknowledges IRQ
+        apu.write_register(0x4015, 0x00);
+        assert!(!apu.irq());
+
+        // Restarted sample is fetched once the buffer is emptied
+        apu.write_register(0x4015, 0x10);
+        assert!(!apu.irq());
+        step_cycles(&mut apu, 54 * 8);
+        assert!(apu.irq());
+        apu.write_register(0x4010, 0x0F);
+        assert!(!apu.irq());
+    }
+
+    #[test]
+    fn frame_irq() {
+        let mut apu = create_apu();
+
+        // Sequencer restarts 3 cycles after a write on even cycle
+        apu.write_register(0x4017, 0x00);
+        step_cycles(&mut apu, 29830);
+        assert!(!apu.irq());
+        step_cycles(&mut apu, 1);
+        assert!(apu.irq());
+
+        // Reading status acknowledges IRQ, but it's raised again on the
+        // remaining cycles of the sequence
+        assert_eq!(apu.read_register(0x4015), 0x40);
+        assert_eq!(apu.read_register(0x4015), 0x00);
+        step_cycles(&mut apu, 1);
+        assert_eq!(apu.read_register(0x4015), 0x40);
+        step_cycles(&mut apu, 1);
+        assert_eq!(apu.read_register(0x4015), 0x40);
+        step_cycles(&mut apu, 1);
+        assert_eq!(apu.read_register(0x4015), 0x00);
+        step_cycles(&mut apu, 29827);
+        assert!(apu.irq());
+
+        // 5-step mode never raises IRQ
+        apu.write_register(0x4017, 0x80);
+        step_cycles(&mut apu, 4);
+        apu.read_register(0x4015);
+        step_cycles(&mut apu, 37282 * 2);
+        assert!(!apu.irq());
+
+        // Inhibit flag clears IRQ immediately
+        apu.write_register(0x4017, 0x00);
+        step_cycles(&mut apu, 29832);
+        assert!(apu.irq());
+        apu.write_register(0x4017, 0x40);
+        assert!(!apu.irq());
+        step_cycles(&mut apu, 29830 * 2);
+        assert!(!apu.irq());
+    }
+
+    #[test]
+    fn five_step_mode() {
+        let mut apu = create_apu();
+        apu.write_register(0x4015, 0x01);
+        apu.write_register(0x4000, 0x00);
+
+        // Writing 5-step mode clocks half frame once the write takes
+        // effect, one cycle later if written on odd cycle
+        apu.write_register(0x4003, 0x18);
+        apu.write_register(0x4017, 0x80);
+        step_cycles(&mut apu, 3);
+        apu.write_register(0x4017, 0x80);
+        step_cycles(&mut apu, 3);
+        assert_eq!(apu.read_register(0x4015), 0x01);
+        step_cycles(&mut apu, 1);
+        assert_eq!(apu.read_register(0x4015), 0x00);
+
+        // Half frames at the second and the last step
+        apu.write_register(0x4003, 0x18);
+        step_cycles(&mut apu, 14913);
+        assert_eq!(apu.read_register(0x4015), 0x01);
+        step_cycles(&mut apu, 1);
+        assert_eq!(apu.read_register(0x4015), 0x01);
+        apu.write_register(0x4003, 0x18);
+        step_cycles(&mut apu, 37281 - 14914);
+        assert_eq!(apu.read_register(0x4015), 0x01);
+        step_cycles(&mut apu, 1);
+        assert_eq!(apu.read_register(0x4015), 0x01);
+        step_cycles(&mut apu, 14914);
+        assert_eq!(apu.read_register(0x4015), 0x00);
+    }
+
+    #[test]
+    fn mixer() {
+        assert_eq!(mixer::mix(&[0.0, 0.0, 0.0, 0.0, 0.0]), 0.0);
+        let pulses = mixer::mix(&[15.0, 15.0, 0.0, 0.0, 0.0]);
+        assert!((pulses - 0.2585).abs() < 0.0001);
+        let tnd = mixer::mix(&[0.0, 0.0, 15.0, 15.0, 127.0]);
+        assert!((tnd - 0.7415).abs() < 0.0001);
+        // Nonlinear: two pulses are quieter than twice one
+        assert!(pulses < mixer::mix(&[15.0, 0.0, 0.0, 0.0, 0.0]) * 2.0);
+    }
+
+    #[test]
+    fn resampler() {
+        let mut resampler = resampler::Resampler::new(1_789_773.0, 48_000.0);
+        let mut samples = Vec::new();
+        for _ in 0..1_789_773 / 10 {
+            resampler.clock();
+        }
+        resampler.add(1.0);
+        for _ in 0..1_789_773 - 1_789_773 / 10 {
+            resampler.clock();
+        }
+        resampler.read_samples(&mut samples);
+        assert_eq!(samples.len(), 47_999);
+
+        // Step is spread over a few samples with small ringing
+        assert!(samples[..4790].iter().all(|s| s.abs() < 0.001));
+        assert!(samples[4820..].iter().all(|s| (s - 1.0).abs() < 0.001));
+        assert!(samples.iter().all(|s| *s > -0.1 && *s < 1.1));
+
+        // Unread output is capped, the oldest samples are dropped
+        resampler.add(0.5);
+        for _ in 0..1_789_773 * 5 {
+            resampler.clock();
+        }
+        samples.clear();
+        resampler.read_samples(&mut samples);
+        assert!(samples.len() > 48_000 && samples.len() < 48_000 * 5);
+        assert!((samples[samples.len() - 1] - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn output_filter() {
+        let dc = |mode| {
+            let mut filters = filter::FilterChain::new(mode, 48_000);
+            (0..48_000).map(|_| filters.process(0.5)).last().unwrap()
+        };
+        assert_eq!(dc(OutputFilter::Raw), 0.5);
+        assert!(dc(OutputFilter::Nes).abs() < 0.0001);
+        assert!(dc(OutputFilter::Famicom).abs() < 0.0001);
+
+        // Low-pass attenuates highest frequencies
+        let mut filters = filter::FilterChain::new(OutputFilter::Famicom, 48_000);
+        let peak = (0..4800)
+            .map(|i| filters.process(if i % 2 == 0 { 0.5 } else { -0.5 }))
+            .skip(4000)
+            .fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!(peak < 0.4);
+    }
+
+    #[test]
+    fn samples() {
+        let mut apu = create_apu();
+        apu.set_output_filter(OutputFilter::Raw);
+        apu.set_sample_rate(48_000);
+        apu.write_register(0x4015, 0x01);
+        apu.write_register(0x4000, 0xBF);
+        apu.write_register(0x4002, 0xFD);
+        apu.write_register(0x4003, 0x00);
+
+        let mut samples = Vec::new();
+        step_cycles(&mut apu, 29830);
+        apu.read_samples(&mut samples);
+        step_cycles(&mut apu, 29830);
+        apu.read_samples(&mut samples);
+        assert!(samples.len() >= 1598 && samples.len() <= 1600);
+        assert!(samples.iter().any(|s| *s > 0.1));
+    }
+
+    #[test]
+    fn channel_controls() {
+        let mut apu = create_apu();
+        apu.set_output_filter(OutputFilter::Raw);
+        apu.set_channel_taps(true);
+        // Silent triangle stays at 15 and DMC level is set directly
+        apu.write_register(0x4011, 0x40);
+
+        let level = |apu: &mut APU| {
+            step_cycles(apu, 1000);
+            let mut samples = Vec::new();
+            apu.read_samples(&mut samples);
+            *samples.last().unwrap()
+        };
+        let same = |a: f32, b: f32| (a - b).abs() < 0.0001;
+
+        let both = level(&mut apu);
+        apu.set_channel_muted(Channel::Dmc, true);
+        let triangle = level(&mut apu);
+        assert!(triangle > 0.0 && triangle < both);
+
+        // Soloed channel can still be muted
+        apu.set_channel_solo(Channel::Dmc, true);
+        assert!(same(level(&mut apu), 0.0));
+        apu.set_channel_muted(Channel::Dmc, false);
+        let dmc = level(&mut apu);
+        assert!(dmc > 0.0 && dmc < both);
+        apu.set_channel_solo(Channel::Triangle, true);
+        assert!(same(level(&mut apu), both));
+
+        apu.set_channel_solo(Channel::Triangle, false);
+        apu.set_channel_solo(Channel::Dmc, false);
+        apu.set_channel_gain(Channel::Dmc, 0.0);
+        assert!(same(level(&mut apu), triangle));
+
+        // Taps ignore mixing settings
+        let mut samples = Vec::new();
+        apu.read_channel_samples(Channel::Triangle, &mut samples);
+        assert!(same(*samples.last().unwrap(), triangle));
+        samples.clear();
+        apu.read_channel_samples(Channel::Dmc, &mut samples);
+        assert!(same(*samples.last().unwrap(), dmc));
+        samples.clear();
+        apu.read_channel_samples(Channel::Noise, &mut samples);
+        assert!(samples.iter().all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn vgm_log() {
+        let mut apu = create_apu();
+        apu.write_register(0x4000, 0xBF);
+        apu.start_vgm_log();
+
+        step_cycles(&mut apu, 29830);
+        apu.write_register(0x4002, 0xFD);
+        apu.write_register(0x4012, 0x00);
+        apu.write_register(0x4013, 0x01);
+        step_cycles(&mut apu, 100);
+        apu.write_register(0x4015, 0x1F);
+        let vgm = apu.stop_vgm_log().unwrap();
+        assert!(apu.stop_vgm_log().is_none());
+
+        assert_eq!(&vgm[0x00..0x04], b"Vgm ");
+        assert_eq!(&vgm[0x04..0x08], &((vgm.len() - 4) as u32).to_le_bytes());
+        assert_eq!(&vgm[0x08..0x0C], &[0x61, 0x01, 0x00, 0x00]);
+        assert_eq!(&vgm[0x84..0x88], &1_789_773u32.to_le_bytes());
+        let samples = (29830 + 100) * 44_100 / 1_789_773u32;
+        assert_eq!(&vgm[0x18..0x1C], &samples.to_le_bytes());
+
+        // Initial state including current one byte sample, then wait
+        // for 735 samples of a frame before the first write
+        let commands = &vgm[0x100..];
+        assert_eq!(&commands[..6], &[0xB4, 0x00, 0xBF, 0xB4, 0x01, 0x00]);
+        let start = 22 * 3 + 10;
+        assert_eq!(&commands[start..start + 4], &[0x62, 0xB4, 0x02, 0xFD]);
+
+        // 17 byte sample is stored once, before the write using it
+        let block = [0x67, 0x66, 0xC2, 19, 0, 0, 0, 0x00, 0xC0];
+        let position = commands
+            .windows(block.len())
+            .position(|w| w == block)
+            .unwrap();
+        assert_eq!(&commands[position + 9..position + 26], &[0xFF; 17]);
+        assert_eq!(&commands[position + 26..position + 29], &[0xB4, 0x13, 0x01]);
+        assert_eq!(commands.windows(3).filter(|w| *w == &block[..3]).count(), 2);
+        assert_eq!(&commands[commands.len() - 4..], &[0xB4, 0x15, 0x1F, 0x66]);
+    }
+
+    #[test]
+    #[ignore = "needs apu_test from nes-test-roms in ../roms"]
+    fn apu_test_rom() {
+        // https://wiki.nesdev.com/w/index.php/Emulator_tests#APU_Tests
+        // Length counters, frame counter IRQ and timing, $4017 write
+        // delay jitter and DMC rates
+        let (code, status) = run_test_rom("apu_test/apu_test").unwrap();
+        assert_eq!(code, 0, "{}", status);
+        assert_eq!(status.trim_end(), "All 8 tests passed");
+    }
+}
@@ -0,0 +1,80 @@
+use super::super::region::Region;
+use super::envelope::Envelope;
+use super::length_counter::LengthCounter;
+
+#[derive(Debug)]
+pub struct Noise {
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+    short_mode: bool,
+    shift: u16, // 15-bit linear feedback shift register
+    timer: u16,
+    period: usize,
+    periods: [u16; 16],
+}
+
+impl Noise {
+    pub fn new() -> Noise {
+        Noise {
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+            short_mode: false,
+            shift: 1,
+            timer: 0,
+            period: 0,
+            periods: Region::default().noise_periods(),
+        }
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.periods = region.noise_periods();
+    }
+
+    // Register is 0-3 for $400C-$400F
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.length.halt = value & 0x20 != 0;
+                self.envelope.write(value);
+            }
+            2 => {
+                self.short_mode = value & 0x80 != 0;
+                self.period = value as usize & 0x0F;
+            }
+            3 => {
+                self.length.load(value >> 3);
+                self.envelope.restart();
+            }
+            _ => {}
+        }
+    }
+
+    // Clocked every CPU cycle
+    pub fn step(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.periods[self.period] - 1;
+            // Short mode takes feedback from bit 6 instead of bit 1
+            let tap = if self.short_mode { 6 } else { 1 };
+            let feedback = (self.shift ^ (self.shift >> tap)) & 1;
+            self.shift = (self.shift >> 1) | (feedback << 14);
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length.active() || self.shift & 1 != 0 {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
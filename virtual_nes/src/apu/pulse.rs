@@ -0,0 +1,136 @@
+use super::envelope::Envelope;
+use super::length_counter::LengthCounter;
+
+// Waveforms for 12.5%, 25%, 50% and 25% negated duty cycles
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+#[derive(Debug)]
+pub struct Pulse {
+    channel: u8, // 1 or 2, sweep negation differs between them
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+    duty: u8,
+    sequence: u8,
+    timer: u16,
+    period: u16,
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_reload: bool,
+    sweep_divider: u8,
+}
+
+impl Pulse {
+    pub fn new(channel: u8) -> Pulse {
+        Pulse {
+            channel: channel,
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+            duty: 0,
+            sequence: 0,
+            timer: 0,
+            period: 0,
+            sweep_enabled: false,
+            sweep_period: 0,
+            sweep_negate: false,
+            sweep_shift: 0,
+            sweep_reload: false,
+            sweep_divider: 0,
+        }
+    }
+
+    // Register is 0-3 for $4000-$4003 or $4004-$4007
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.duty = value >> 6;
+                self.length.halt = value & 0x20 != 0;
+                self.envelope.write(value);
+            }
+            1 => {
+                self.sweep_enabled = value & 0x80 != 0;
+                self.sweep_period = (value >> 4) & 0x07;
+                self.sweep_negate = value & 0x08 != 0;
+                self.sweep_shift = value & 0x07;
+                self.sweep_reload = true;
+            }
+            2 => self.period = (self.period & 0x0700) | value as u16,
+            3 => {
+                self.period = (self.period & 0x00FF) | ((value as u16 & 0x07) << 8);
+                self.length.load(value >> 3);
+                self.envelope.restart();
+                self.sequence = 0;
+            }
+            _ => {}
+        }
+    }
+
+    // Clocked every APU cycle (two CPU cycles)
+    pub fn step(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.sequence = (self.sequence + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+        self.clock_sweep();
+    }
+
+    fn clock_sweep(&mut self) {
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.muted() {
+            self.period = self.sweep_target();
+        }
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    // Pulse 1 negates with ones' complement, so it subtracts one more
+    fn sweep_target(&self) -> u16 {
+        let change = self.period >> self.sweep_shift;
+        if !self.sweep_negate {
+            self.period + change
+        } else if self.channel == 1 {
+            (self.period - change).saturating_sub(1)
+        } else {
+            self.period - change
+        }
+    }
+
+    pub fn get_period(&self) -> u16 {
+        self.period
+    }
+
+    // Sweep unit silences the channel even when it is disabled
+    fn muted(&self) -> bool {
+        self.period < 8 || self.sweep_target() > 0x07FF
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length.active()
+            || self.muted()
+            || DUTY_TABLE[self.duty as usize][self.sequence as usize] == 0
+        {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
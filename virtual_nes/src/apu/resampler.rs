@@ -0,0 +1,120 @@
+use std::f64::consts::PI;
+
+// Kernel resolution between output samples and its width in samples
+const PHASES: usize = 64;
+const TAPS: usize = 16;
+// Fraction of output rate kept below Nyquist
+const CUTOFF: f64 = 0.45;
+// Unread output samples kept, about 2.7 seconds at 48 kHz
+const MAX_SAMPLES: usize = 1 << 17;
+
+// Band-limited synthesis from amplitude changes at input clock rate.
+// Every change is added as a windowed sinc impulse to a buffer of output
+// sample deltas, which are integrated into samples once no further
+// change can affect them.
+#[derive(Debug)]
+pub struct Resampler {
+    kernel: Vec<[f32; TAPS]>,
+    factor: f64, // output samples per input clock
+    time: f64,   // position in output samples relative to buffer start
+    buffer: Vec<f32>,
+    amplitude: f32,
+    integrator: f32,
+}
+
+impl Resampler {
+    pub fn new(clock_rate: f64, sample_rate: f64) -> Resampler {
+        Resampler {
+            kernel: create_kernel(),
+            factor: sample_rate / clock_rate,
+            time: 0.0,
+            buffer: vec![0.0; TAPS],
+            amplitude: 0.0,
+            integrator: 0.0,
+        }
+    }
+
+    pub fn set_rates(&mut self, clock_rate: f64, sample_rate: f64) {
+        self.factor = sample_rate / clock_rate;
+    }
+
+    // Sets amplitude at current time
+    pub fn add(&mut self, amplitude: f32) {
+        let delta = amplitude - self.amplitude;
+        if delta == 0.0 {
+            return;
+        }
+        self.amplitude = amplitude;
+
+        let index = self.time as usize;
+        let phase = ((self.time - index as f64) * PHASES as f64) as usize;
+        if self.buffer.len() < index + TAPS {
+            self.buffer.resize(index + TAPS, 0.0);
+        }
+        for (i, k) in self.kernel[phase].iter().enumerate() {
+            self.buffer[index + i] += delta * k;
+        }
+    }
+
+    // Advances one input clock
+    pub fn clock(&mut self) {
+        self.time += self.factor;
+        if self.time as usize > MAX_SAMPLES {
+            self.drop_samples(MAX_SAMPLES / 2);
+        }
+    }
+
+    // Output nobody reads is dropped oldest first,
+    // its deltas still count towards the current level
+    fn drop_samples(&mut self, count: usize) {
+        let end = count.min(self.buffer.len());
+        for delta in self.buffer.drain(..end) {
+            self.integrator += delta;
+        }
+        self.time -= count as f64;
+    }
+
+    // Appends samples that are complete so far
+    pub fn read_samples(&mut self, samples: &mut Vec<f32>) {
+        let count = self.time as usize;
+        if self.buffer.len() < count {
+            self.buffer.resize(count, 0.0);
+        }
+        for delta in self.buffer.drain(..count) {
+            self.integrator += delta;
+            samples.push(self.integrator);
+        }
+        self.time -= count as f64;
+    }
+}
+
+// Windowed sinc impulses for every phase, each normalized to unit sum
+// so that integrated output settles exactly on the new amplitude
+fn create_kernel() -> Vec<[f32; TAPS]> {
+    let half = (TAPS / 2) as f64;
+    (0..PHASES)
+        .map(|phase| {
+            let offset = phase as f64 / PHASES as f64;
+            let mut taps = [0.0; TAPS];
+            let mut sum = 0.0;
+            for (i, tap) in taps.iter_mut().enumerate() {
+                let x = i as f64 - half - offset + 1.0;
+                let sinc = if x == 0.0 {
+                    2.0 * CUTOFF
+                } else {
+                    (2.0 * PI * CUTOFF * x).sin() / (PI * x)
+                };
+                // Blackman window
+                let w = 2.0 * PI * (x / TAPS as f64 + 0.5);
+                let window = 0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos();
+                *tap = sinc * window;
+                sum += *tap;
+            }
+            let mut kernel = [0.0; TAPS];
+            for (k, tap) in kernel.iter_mut().zip(taps.iter()) {
+                *k = (tap / sum) as f32;
+            }
+            kernel
+        })
+        .collect()
+}
@@ -0,0 +1,72 @@
+use super::length_counter::LengthCounter;
+
+const TRIANGLE_TABLE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+    13, 14, 15,
+];
+
+#[derive(Debug, Default)]
+pub struct Triangle {
+    pub length: LengthCounter,
+    control: bool, // also halts length counter
+    linear_period: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+    sequence: u8,
+    timer: u16,
+    period: u16,
+}
+
+impl Triangle {
+    // Register is 0-3 for $4008-$400B
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.control = value & 0x80 != 0;
+                self.length.halt = self.control;
+                self.linear_period = value & 0x7F;
+            }
+            2 => self.period = (self.period & 0x0700) | value as u16,
+            3 => {
+                self.period = (self.period & 0x00FF) | ((value as u16 & 0x07) << 8);
+                self.length.load(value >> 3);
+                self.linear_reload = true;
+            }
+            _ => {}
+        }
+    }
+
+    // Clocked every CPU cycle
+    pub fn step(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            // Ultrasonic periods would only produce a pop at the mixer,
+            // so the sequencer is held like most emulators do
+            if self.length.active() && self.linear_counter > 0 && self.period >= 2 {
+                self.sequence = (self.sequence + 1) % 32;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_period;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    // Silenced triangle keeps outputting its current step
+    pub fn output(&self) -> u8 {
+        TRIANGLE_TABLE[self.sequence as usize]
+    }
+}
@@ -0,0 +1,128 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+
+// VGM timestamps are always in 44.1 kHz samples
+const VGM_RATE: u64 = 44_100;
+const VERSION: u32 = 0x0000_0161;
+const HEADER_SIZE: usize = 0x100;
+
+const CMD_NES_APU: u8 = 0xB4;
+const CMD_WAIT: u8 = 0x61;
+const CMD_WAIT_NTSC_FRAME: u8 = 0x62;
+const CMD_WAIT_PAL_FRAME: u8 = 0x63;
+const CMD_WAIT_SHORT: u8 = 0x70;
+const CMD_DATA_BLOCK: u8 = 0x67;
+const CMD_END: u8 = 0x66;
+const BLOCK_NES_RAM: u8 = 0xC2;
+
+// Records APU register writes and DPCM sample memory as VGM commands.
+//
+// https://vgmrips.net/wiki/VGM_Specification
+#[derive(Debug)]
+pub struct VgmLogger {
+    clock: u64,       // CPU cycles per second
+    start_cycle: u64, // APU cycle when logging started
+    samples: u64,     // timestamp of last command
+    commands: Vec<u8>,
+    dpcm: Vec<Option<u8>>, // $8000-$FFFF as known by the player
+}
+
+impl VgmLogger {
+    pub fn new(clock: u64, start_cycle: u64) -> VgmLogger {
+        VgmLogger {
+            clock: clock,
+            start_cycle: start_cycle,
+            samples: 0,
+            commands: Vec::new(),
+            dpcm: vec![None; 0x8000],
+        }
+    }
+
+    fn wait_until(&mut self, cycle: u64) {
+        let samples = (cycle - self.start_cycle) * VGM_RATE / self.clock;
+        let mut wait = samples - self.samples;
+        self.samples = samples;
+
+        while wait > 0 {
+            match wait {
+                735 => {
+                    self.commands.push(CMD_WAIT_NTSC_FRAME);
+                    wait = 0;
+                }
+                882 => {
+                    self.commands.push(CMD_WAIT_PAL_FRAME);
+                    wait = 0;
+                }
+                1..=16 => {
+                    self.commands.push(CMD_WAIT_SHORT + wait as u8 - 1);
+                    wait = 0;
+                }
+                _ => {
+                    let chunk = wait.min(0xFFFF);
+                    self.commands.push(CMD_WAIT);
+                    self.commands
+                        .write_u16::<LittleEndian>(chunk as u16)
+                        .unwrap();
+                    wait -= chunk;
+                }
+            }
+        }
+    }
+
+    // Register write at $4000-$401F
+    pub fn write(&mut self, cycle: u64, address: u16, value: u8) {
+        self.wait_until(cycle);
+        self.commands
+            .extend_from_slice(&[CMD_NES_APU, (address - 0x4000) as u8, value]);
+    }
+
+    // Stores DPCM sample unless the player already has it in memory
+    pub fn sample(&mut self, cycle: u64, address: u16, data: &[u8]) {
+        let start = address as usize - 0x8000;
+        let known = data
+            .iter()
+            .enumerate()
+            .all(|(i, value)| self.dpcm.get(start + i) == Some(&Some(*value)));
+        if known || data.is_empty() {
+            return;
+        }
+
+        self.wait_until(cycle);
+        self.commands
+            .extend_from_slice(&[CMD_DATA_BLOCK, CMD_END, BLOCK_NES_RAM]);
+        self.commands
+            .write_u32::<LittleEndian>(data.len() as u32 + 2)
+            .unwrap();
+        self.commands.write_u16::<LittleEndian>(address).unwrap();
+        self.commands.extend_from_slice(data);
+        for (i, value) in data.iter().enumerate() {
+            if let Some(known) = self.dpcm.get_mut(start + i) {
+                *known = Some(*value);
+            }
+        }
+    }
+
+    // Completes the file at given cycle, frame rate is 60 or 50
+    pub fn finish(mut self, cycle: u64, frame_rate: u32) -> Vec<u8> {
+        self.wait_until(cycle);
+        self.commands.push(CMD_END);
+
+        let mut header = vec![0; HEADER_SIZE];
+        let size = (HEADER_SIZE + self.commands.len()) as u32;
+        {
+            let mut field = |offset: usize, value: u32| {
+                (&mut header[offset..offset + 4])
+                    .write_u32::<LittleEndian>(value)
+                    .unwrap();
+            };
+            field(0x04, size - 4); // EOF offset
+            field(0x08, VERSION);
+            field(0x18, self.samples as u32);
+            field(0x24, frame_rate);
+            field(0x34, HEADER_SIZE as u32 - 0x34); // data offset
+            field(0x84, self.clock as u32); // NES APU clock
+        }
+        header[0..4].copy_from_slice(b"Vgm ");
+        header.extend_from_slice(&self.commands);
+        header
+    }
+}
@@ -0,0 +1,72 @@
+/// Everything the 6502 core is connected to. Every read and write
+/// is one CPU cycle, the bus is ticked right before the access.
+pub trait Bus {
+    // Access with side effects, e.g. reading a status register clears it
+    fn read(&mut self, address: u16) -> u8;
+    fn write(&mut self, address: u16, value: u8);
+
+    // Access without side effects, for debuggers and disassembly
+    fn peek(&self, address: u16) -> u8;
+
+    fn peek_word(&self, address: u16) -> u16 {
+        let lo = self.peek(address) as u16;
+        let hi = self.peek(address.wrapping_add(1)) as u16;
+        (hi << 8) | lo
+    }
+
+    // Advances attached devices by one CPU cycle,
+    // returns true on the rising edge of NMI
+    fn tick(&mut self) -> bool {
+        false
+    }
+
+    // Level of the IRQ line
+    fn irq(&self) -> bool {
+        false
+    }
+
+    // Asked on every read cycle, returns cycles CPU has to be halted
+    // for before the read, e.g. while DMA owns the bus
+    fn take_stall(&mut self) -> usize {
+        0
+    }
+
+    // Address of the first access to unmapped memory since previous call
+    fn take_fault(&mut self) -> Option<u16> {
+        None
+    }
+}
+
+/// Flat 64K of RAM without any devices.
+#[derive(Debug)]
+pub struct Ram {
+    pub data: Vec<u8>,
+}
+
+impl Ram {
+    pub fn new() -> Ram {
+        Ram {
+            data: vec![0; 0x10000],
+        }
+    }
+}
+
+impl Default for Ram {
+    fn default() -> Ram {
+        Ram::new()
+    }
+}
+
+impl Bus for Ram {
+    fn read(&mut self, address: u16) -> u8 {
+        self.data[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.data[address as usize] = value;
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.data[address as usize]
+    }
+}
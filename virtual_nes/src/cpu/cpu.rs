@@ -1,12 +1,13 @@
-use super::super::memory::Memory;
+use super::bus::Bus;
 use super::description::{AddressingMode, DESCRIPTIONS};
-use super::implementation::IMPLEMENTATIONS;
-use super::memory::CpuMemory;
 use super::description::Instruction;
+use std::error;
+use std::fmt;
 
+/// 6502 core, runs instructions cycle by cycle on the given bus.
 #[derive(Debug)]
-pub struct CPU {
-    pub mem: CpuMemory,
+pub struct CPU<B: Bus> {
+    pub mem: B,
 	pub pc: u16, // program counter
     pub sp: u8,  // stack pointer
     pub a: u8,   // accumulator
@@ -20,18 +21,44 @@ pub struct CPU {
     pub n: u8,   // negative flag
     b: u8,       // unused flag
     u: u8,       // unused flag
-    interrupt: Interrupt,
+    nmi_pending: bool,  // NMI edge latched until serviced
+    need_nmi: bool,     // NMI and IRQ polled at the end of every cycle
+    prev_need_nmi: bool,
+    run_irq: bool,
+    prev_run_irq: bool,
+    interrupt: bool,    // taken before next instruction
     cycles: usize,
-    stall: usize,
+    stop: Option<StopReason>, // CPU is halted until reset
 }
 
-#[derive(Debug)]
-pub enum Interrupt {
-    None,
-    NMI,
-    IRQ,
+/// Why CPU stopped executing instructions. The CPU stays halted
+/// and keeps returning the reason until reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    // KIL/JAM opcode locks up the real CPU
+    Jammed { pc: u16, opcode: u8 },
+    // Access to address without anything mapped to it, only reported
+    // by buses debugging such accesses
+    BusFault { pc: u16, opcode: u8, address: u16 },
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StopReason::Jammed { pc, opcode } => {
+                write!(f, "CPU jammed by op 0x{:02X} at 0x{:04X}", opcode, pc)
+            }
+            StopReason::BusFault { pc, opcode, address } => write!(
+                f,
+                "Unhandled access to 0x{:04X} by op 0x{:02X} at 0x{:04X}",
+                address, opcode, pc
+            ),
+        }
+    }
 }
 
+impl error::Error for StopReason {}
+
 #[derive(Debug)]
 pub struct Info {
     pub address: u16,
@@ -51,8 +78,8 @@ impl Info {
     }
 }
 
-impl CPU {
-    pub fn new(mem: CpuMemory) -> CPU {
+impl<B: Bus> CPU<B> {
+    pub fn new(mem: B) -> CPU<B> {
         CPU {
             mem: mem,
             pc: 0,
@@ -68,22 +95,42 @@ impl CPU {
             n: 0,
             b: 0,
             u: 0,
-            interrupt: Interrupt::None,
+            nmi_pending: false,
+            need_nmi: false,
+            prev_need_nmi: false,
+            run_irq: false,
+            prev_run_irq: false,
+            interrupt: false,
             cycles: 0,
-            stall: 0,
+            stop: None,
         }
     }
 
     pub fn reset(&mut self) {
-        self.pc = self.mem.read_word(0xFFFC);
+        self.pc = self.mem.peek_word(0xFFFC);
         self.sp = 0xFD;
         self.set_flags(0x24);
+        self.stop = None;
+        self.mem.take_fault();
+        self.nmi_pending = false;
+        self.interrupt = false;
+    }
+
+    // Halts CPU until reset, first reason wins
+    pub fn halt(&mut self, reason: StopReason) {
+        if self.stop.is_none() {
+            self.stop = Some(reason);
+        }
+    }
+
+    pub fn get_stop_reason(&self) -> Option<StopReason> {
+        self.stop
     }
 
     // Push byte onto stack
     pub fn push(&mut self, value: u8) {
         let address = self.sp as u16 | 0x100;
-        self.mem.write(address, value);
+        self.write(address, value);
         self.sp = self.sp.wrapping_sub(1);
     }
 
@@ -153,12 +200,20 @@ impl CPU {
 		self.set_n(value);
 	}
 
-	// adds a cycle for taking a branch and adds another cycle
-	// if the branch jumps to a new page
+	// taken branch reads next opcode while adding the offset, and
+	// reads again with unfixed high byte if the branch jumps to a new page.
+	// Interrupts are not polled on that cycle, so IRQ or NMI raised
+	// during the branch waits for one more instruction.
 	pub fn add_branch_cycles(&mut self, info: &Info) {
-		self.cycles += 1;
+		if self.run_irq && !self.prev_run_irq {
+			self.run_irq = false;
+		}
+		if self.need_nmi && !self.prev_need_nmi {
+			self.need_nmi = false;
+		}
+		self.read(info.pc);
 		if is_different_pages(info.pc, info.address) {
-			self.cycles += 1;
+			self.read(info.pc & 0xFF00 | info.address & 0x00FF);
 		}
 	}
 
@@ -174,124 +229,182 @@ impl CPU {
         }
     }
 
-    // NMI - Non-Maskable Interrupt
-    pub fn nmi(&mut self) {
-        self.push_word(self.pc);
-        self.php(&Info::new());
-        self.pc = self.mem.read_word(0xFFFA);
-        self.i = 1;
-        self.cycles += 7;
+    // NMI or IRQ, opcode and operand fetches are replaced by dummy reads
+    fn hardware_interrupt(&mut self) {
+        self.read(self.pc);
+        self.read(self.pc);
+        self.interrupt(self.pc, false);
     }
 
-    // IRQ - IRQ Interrupt
-    pub fn irq(&mut self) {
-        self.push_word(self.pc);
-        self.php(&Info::new());
-        self.pc = self.mem.read_word(0xFFFE);
+    // BRK, NMI and IRQ push PC and flags, B flag is set only for BRK.
+    // NMI detected before the flags are pushed hijacks the vector.
+    pub fn interrupt(&mut self, pc: u16, brk: bool) {
+        self.push_word(pc);
+        let nmi = self.nmi_pending;
+        let flags = match brk {
+            true => self.get_flags() | 0x30,
+            false => self.get_flags() & 0xEF | 0x20,
+        };
+        self.push(flags);
         self.i = 1;
-        self.cycles += 7;
+        let vector = match nmi {
+            true => 0xFFFA,
+            false => 0xFFFE,
+        };
+        if nmi {
+            self.nmi_pending = false;
+        }
+        self.pc = self.read_word(vector);
+
+        // first instruction of the handler runs before the next interrupt
+        self.need_nmi = false;
+        self.prev_need_nmi = false;
+        self.run_irq = false;
+        self.prev_run_irq = false;
     }
 
+    // Rising edge of the NMI line
     pub fn trigger_nmi(&mut self) {
-        self.interrupt = Interrupt::NMI;
+        self.nmi_pending = true;
     }
 
-    pub fn trigger_irq(&mut self) {
-        self.interrupt = Interrupt::IRQ;
+    // Runs the rest of the bus for one CPU cycle
+    // and polls interrupt lines at its end
+    fn tick(&mut self) {
+        self.cycles += 1;
+        if self.mem.tick() {
+            self.trigger_nmi();
+        }
+
+        self.prev_need_nmi = self.need_nmi;
+        self.need_nmi = self.nmi_pending;
+        self.prev_run_irq = self.run_irq;
+        self.run_irq = self.mem.irq() && self.i == 0;
     }
 
-    // Controller and PPU needs to be mutable while reading
+    // Every read and write is a bus cycle, the rest of the bus
+    // advances before the access.
+    // DMA halts CPU only on a read, which is done once the DMA is over
     pub fn read(&mut self, address: u16) -> u8 {
-        match address {
-            0x2000..0x4000 => self.mem.ppu.read_register(0x2000 + address % 8),
-            0x4014 => self.mem.ppu.read_register(address),
-            0x4016 => self.mem.controller_1.read(),
-            0x4017 => self.mem.controller_2.read(),
-            _ => self.mem.read(address),
+        self.tick();
+        let mut stall = self.mem.take_stall();
+        while stall > 0 {
+            stall -= 1;
+            self.tick();
+            stall += self.mem.take_stall();
         }
+        self.mem.read(address)
     }
 
-    // DMA demands CPU access
     pub fn write(&mut self, address: u16, value: u8) {
-        match address {
-            _ => self.mem.write(address, value),
-        }
+        self.tick();
+        self.mem.write(address, value);
+    }
+
+    // Little endian word in two bus cycles
+    pub fn read_word(&mut self, address: u16) -> u16 {
+        let lo = self.read(address) as u16;
+        let hi = self.read(address.wrapping_add(1)) as u16;
+        (hi << 8) | lo
     }
 
-    // returns operand address based on addressing mode
-    // and extra cycles count when pages is crossed
-    fn get_address(&mut self, pc: u16, instruction: &Instruction) -> (u16, usize) {
-        let mut page_crossed = false;
-        let address = match instruction.addressing {
-            AddressingMode::Absolute => self.mem.read_word(pc),
+    // Bug in 6502: low byte wrapping without high increment
+    fn read_word_bug(&mut self, address: u16) -> u16 {
+        let lo = self.read(address) as u16;
+        let hi = self.read((address & 0xFF00) | (address.wrapping_add(1) & 0xFF)) as u16;
+        (hi << 8) | lo
+    }
+
+    // returns operand address based on addressing mode.
+    // Indexing reads from the address before its high byte is fixed,
+    // read instructions skip it when no page is crossed.
+    fn get_address(&mut self, pc: u16, instruction: &Instruction) -> u16 {
+        // only read instructions take extra cycle on page crossing
+        let reads = instruction.page_cycles > 0;
+        match instruction.addressing {
+            AddressingMode::Absolute => self.read_word(pc),
             AddressingMode::AbsoluteX => {
-                let addr = self.mem.read_word(pc).wrapping_add(self.x as u16);
-                page_crossed = is_different_pages(addr - self.x as u16, addr);
-                addr
+                let base = self.read_word(pc);
+                self.index(base, self.x, reads)
             }
             AddressingMode::AbsoluteY => {
-                let addr = self.mem.read_word(pc).wrapping_add(self.y as u16);
-                page_crossed = is_different_pages(addr.wrapping_sub(self.y as u16), addr);
-                addr
+                let base = self.read_word(pc);
+                self.index(base, self.y, reads)
+            }
+            AddressingMode::Accumulator | AddressingMode::Implied => {
+                // next byte is read and thrown away
+                self.read(pc);
+                0
             }
-            AddressingMode::Accumulator => 0,
             AddressingMode::Immediate => pc,
-            AddressingMode::Implied => 0,
             AddressingMode::IndexedIndirect => {
-                let addr = (self.mem.read(pc) as u16).wrapping_add(self.x as u16) & 0xFF;
-                self.mem.read_word_bug(addr)
+                let zero_page = self.read(pc);
+                self.read(zero_page as u16);
+                let addr = zero_page.wrapping_add(self.x) as u16;
+                self.read_word_bug(addr)
             }
             AddressingMode::Indirect => {
-                let addr = self.mem.read_word(pc);
-                self.mem.read_word_bug(addr)
+                let addr = self.read_word(pc);
+                self.read_word_bug(addr)
             }
             AddressingMode::IndirectIndexed => {
-                let addr = self.mem.read_word_bug(self.mem.read(pc) as u16).wrapping_add(self.y as u16);
-                page_crossed = is_different_pages(addr.wrapping_sub(self.y as u16), addr);
-                addr
+                let zero_page = self.read(pc);
+                let base = self.read_word_bug(zero_page as u16);
+                self.index(base, self.y, reads)
             }
             AddressingMode::Relative => {
-                match self.mem.read(pc) as u16 {
+                match self.read(pc) as u16 {
                     offset if offset < 0x80 => self.pc.wrapping_add(2).wrapping_add(offset),
                     offset => self.pc.wrapping_add(2).wrapping_add(offset).wrapping_sub(0x100)
                 }
             }
-            AddressingMode::ZeroPage => self.mem.read(pc) as u16,
-            AddressingMode::ZeroPageX => (self.mem.read(pc).wrapping_add(self.x)) as u16 & 0xFF,
-            AddressingMode::ZeroPageY => (self.mem.read(pc).wrapping_add(self.y)) as u16 & 0xFF,
-        };
-
-        let cycles = match page_crossed {
-            true => instruction.page_cycles,
-            false => 0, 
-        };
+            AddressingMode::ZeroPage => self.read(pc) as u16,
+            AddressingMode::ZeroPageX => {
+                let zero_page = self.read(pc);
+                self.read(zero_page as u16);
+                zero_page.wrapping_add(self.x) as u16
+            }
+            AddressingMode::ZeroPageY => {
+                let zero_page = self.read(pc);
+                self.read(zero_page as u16);
+                zero_page.wrapping_add(self.y) as u16
+            }
+        }
+    }
 
-        (address, cycles)
+    // adds index to base address, with the dummy read from the
+    // address which has only its low byte added
+    fn index(&mut self, base: u16, index: u8, reads: bool) -> u16 {
+        let address = base.wrapping_add(index as u16);
+        if !reads || is_different_pages(base, address) {
+            self.read(base & 0xFF00 | address & 0x00FF);
+        }
+        address
     }
 
-    pub fn step(&mut self) -> usize {
-        if self.stall > 0 {
-            self.stall -= 1;
-            return 1
+    pub fn step(&mut self) -> Result<usize, StopReason> {
+        if let Some(reason) = self.stop {
+            return Err(reason);
         }
 
         let cycles = self.cycles;
 
-        match self.interrupt {
-            Interrupt::NMI => self.nmi(),
-            Interrupt::IRQ => self.irq(),
-            _ => {}
+        if self.interrupt {
+            self.interrupt = false;
+            self.hardware_interrupt();
         }
-        self.interrupt = Interrupt::None;
 
-        let opcode = self.mem.read(self.pc) as usize;
+        let start = self.pc;
+        let opcode = self.read(self.pc) as usize;
         let instruction = &DESCRIPTIONS[opcode];
-        let fun = &IMPLEMENTATIONS[opcode];
+        let fun = Self::IMPLEMENTATIONS[opcode];
 
         let pc = self.pc.wrapping_add(1);
-        let (address, page_cycles) = self.get_address(pc, instruction);
-        self.cycles += instruction.cycles;
-        self.cycles += page_cycles;
+        // JSR fetches the high byte of its target by itself
+        let address = match opcode {
+            0x20 => self.read(pc) as u16,
+            _ => self.get_address(pc, instruction),
+        };
         self.pc += instruction.size;
         
         let info = Info {
@@ -306,15 +419,31 @@ impl CPU {
         // self.dump_regs(&info);
         // println!("{}", self.print_instruction());
 
-        self.cycles - cycles
+        // Interrupt lines are checked as they were at the end of
+        // the second-to-last cycle. I flag changed by CLI, SEI and PLP
+        // on the last cycle affects the next instruction only.
+        self.interrupt = self.prev_need_nmi || self.prev_run_irq;
+
+        if let Some(address) = self.mem.take_fault() {
+            self.halt(StopReason::BusFault {
+                pc: start,
+                opcode: opcode as u8,
+                address: address,
+            });
+        }
+
+        match self.stop {
+            Some(reason) => Err(reason),
+            None => Ok(self.cycles - cycles),
+        }
     }
 
     #[cfg(test)]
     pub fn print_instruction(&mut self) -> String {
-        let opcode = self.read(self.pc);
+        let opcode = self.mem.peek(self.pc);
         let instruction = &DESCRIPTIONS[opcode as usize];
-        let o1 = self.read(self.pc + 1);
-        let o2 = self.read(self.pc + 2);
+        let o1 = self.mem.peek(self.pc + 1);
+        let o2 = self.mem.peek(self.pc + 2);
         let w0 = format!("{:02X}", opcode);
         let mut w1 = format!("{:02X}", o1);
         let mut w2 = format!("{:02X}", o2);
@@ -326,9 +455,9 @@ impl CPU {
         }
 
         let operands = if instruction.size == 2 {
-            format!("{:02X}", self.read(self.pc + 1) as u16)
+            format!("{:02X}", self.mem.peek(self.pc + 1) as u16)
         } else if instruction.size == 3 {
-            let a = (self.read(self.pc + 2) as u16) << 8 | self.read(self.pc + 1) as u16;
+            let a = (self.mem.peek(self.pc + 2) as u16) << 8 | self.mem.peek(self.pc + 1) as u16;
             format!("{:04X}", a)
         } else {
             "".to_string()
@@ -337,7 +466,7 @@ impl CPU {
         let operand = match instruction.addressing {
             AddressingMode::Absolute => format!("${}", operands),
             AddressingMode::Immediate => format!("#${}", operands),
-            AddressingMode::ZeroPage => format!("${} = {:02X}", operands, self.read(o1 as u16)),
+            AddressingMode::ZeroPage => format!("${} = {:02X}", operands, self.mem.peek(o1 as u16)),
             AddressingMode::Relative =>  {
                 let address = match o1 as u16 {
                     offset if offset < 0x80 => self.pc + 2 + offset,
@@ -347,9 +476,11 @@ impl CPU {
             }
             AddressingMode::Accumulator => "A".to_string(),
             AddressingMode::IndirectIndexed => {
-                let addr = self.mem.read_word_bug(o1 as u16).wrapping_add(self.y as u16);
-                let addr2 = self.mem.read_word(o1 as u16).wrapping_add(self.y as u16);
-                let value = self.read(addr);
+                // pointer wraps around in zero page
+                let pointer = (self.mem.peek(o1.wrapping_add(1) as u16) as u16) << 8 | self.mem.peek(o1 as u16) as u16;
+                let addr = pointer.wrapping_add(self.y as u16);
+                let addr2 = self.mem.peek_word(o1 as u16).wrapping_add(self.y as u16);
+                let value = self.mem.peek(addr);
                 format!("(${:02X}),Y = {:04X} @ {:04X} = {:02X}", o1, addr2, addr, value)
             }
             _ => "".to_string(),
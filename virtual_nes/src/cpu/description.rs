@@ -37,7 +37,7 @@ Instruction {opcode: 0x07, name: "SLO", size: 2, cycles: 5, page_cycles: 0, addr
 Instruction {opcode: 0x08, name: "PHP", size: 1, cycles: 3, page_cycles: 0, addressing: AddressingMode::Implied,},
 Instruction {opcode: 0x09, name: "ORA", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x0A, name: "ASL", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Accumulator,},
-Instruction {opcode: 0x0B, name: "ANC", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
+Instruction {opcode: 0x0B, name: "ANC", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x0C, name: "NOP", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0x0D, name: "ORA", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0x0E, name: "ASL", size: 3, cycles: 6, page_cycles: 0, addressing: AddressingMode::Absolute,},
@@ -69,7 +69,7 @@ Instruction {opcode: 0x27, name: "RLA", size: 2, cycles: 5, page_cycles: 0, addr
 Instruction {opcode: 0x28, name: "PLP", size: 1, cycles: 4, page_cycles: 0, addressing: AddressingMode::Implied,},
 Instruction {opcode: 0x29, name: "AND", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x2A, name: "ROL", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Accumulator,},
-Instruction {opcode: 0x2B, name: "ANC", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
+Instruction {opcode: 0x2B, name: "ANC", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x2C, name: "BIT", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0x2D, name: "AND", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0x2E, name: "ROL", size: 3, cycles: 6, page_cycles: 0, addressing: AddressingMode::Absolute,},
@@ -101,7 +101,7 @@ Instruction {opcode: 0x47, name: "SRE", size: 2, cycles: 5, page_cycles: 0, addr
 Instruction {opcode: 0x48, name: "PHA", size: 1, cycles: 3, page_cycles: 0, addressing: AddressingMode::Implied,},
 Instruction {opcode: 0x49, name: "EOR", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x4A, name: "LSR", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Accumulator,},
-Instruction {opcode: 0x4B, name: "ALR", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
+Instruction {opcode: 0x4B, name: "ALR", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x4C, name: "JMP", size: 3, cycles: 3, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0x4D, name: "EOR", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0x4E, name: "LSR", size: 3, cycles: 6, page_cycles: 0, addressing: AddressingMode::Absolute,},
@@ -133,7 +133,7 @@ Instruction {opcode: 0x67, name: "RRA", size: 2, cycles: 5, page_cycles: 0, addr
 Instruction {opcode: 0x68, name: "PLA", size: 1, cycles: 4, page_cycles: 0, addressing: AddressingMode::Implied,},
 Instruction {opcode: 0x69, name: "ADC", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x6A, name: "ROR", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Accumulator,},
-Instruction {opcode: 0x6B, name: "ARR", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
+Instruction {opcode: 0x6B, name: "ARR", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x6C, name: "JMP", size: 3, cycles: 5, page_cycles: 0, addressing: AddressingMode::Indirect,},
 Instruction {opcode: 0x6D, name: "ADC", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0x6E, name: "ROR", size: 3, cycles: 6, page_cycles: 0, addressing: AddressingMode::Absolute,},
@@ -156,16 +156,16 @@ Instruction {opcode: 0x7E, name: "ROR", size: 3, cycles: 7, page_cycles: 0, addr
 Instruction {opcode: 0x7F, name: "RRA", size: 3, cycles: 7, page_cycles: 0, addressing: AddressingMode::AbsoluteX,},
 Instruction {opcode: 0x80, name: "NOP", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x81, name: "STA", size: 2, cycles: 6, page_cycles: 0, addressing: AddressingMode::IndexedIndirect,},
-Instruction {opcode: 0x82, name: "NOP", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
+Instruction {opcode: 0x82, name: "NOP", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x83, name: "SAX", size: 2, cycles: 6, page_cycles: 0, addressing: AddressingMode::IndexedIndirect,},
 Instruction {opcode: 0x84, name: "STY", size: 2, cycles: 3, page_cycles: 0, addressing: AddressingMode::ZeroPage,},
 Instruction {opcode: 0x85, name: "STA", size: 2, cycles: 3, page_cycles: 0, addressing: AddressingMode::ZeroPage,},
 Instruction {opcode: 0x86, name: "STX", size: 2, cycles: 3, page_cycles: 0, addressing: AddressingMode::ZeroPage,},
 Instruction {opcode: 0x87, name: "SAX", size: 2, cycles: 3, page_cycles: 0, addressing: AddressingMode::ZeroPage,},
 Instruction {opcode: 0x88, name: "DEY", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Implied,},
-Instruction {opcode: 0x89, name: "NOP", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
+Instruction {opcode: 0x89, name: "NOP", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x8A, name: "TXA", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Implied,},
-Instruction {opcode: 0x8B, name: "XAA", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
+Instruction {opcode: 0x8B, name: "XAA", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0x8C, name: "STY", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0x8D, name: "STA", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0x8E, name: "STX", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
@@ -173,7 +173,7 @@ Instruction {opcode: 0x8F, name: "SAX", size: 3, cycles: 4, page_cycles: 0, addr
 Instruction {opcode: 0x90, name: "BCC", size: 2, cycles: 2, page_cycles: 1, addressing: AddressingMode::Relative,},
 Instruction {opcode: 0x91, name: "STA", size: 2, cycles: 6, page_cycles: 0, addressing: AddressingMode::IndirectIndexed,},
 Instruction {opcode: 0x92, name: "KIL", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Implied,},
-Instruction {opcode: 0x93, name: "AHX", size: 0, cycles: 6, page_cycles: 0, addressing: AddressingMode::IndirectIndexed,},
+Instruction {opcode: 0x93, name: "AHX", size: 2, cycles: 6, page_cycles: 0, addressing: AddressingMode::IndirectIndexed,},
 Instruction {opcode: 0x94, name: "STY", size: 2, cycles: 4, page_cycles: 0, addressing: AddressingMode::ZeroPageX,},
 Instruction {opcode: 0x95, name: "STA", size: 2, cycles: 4, page_cycles: 0, addressing: AddressingMode::ZeroPageX,},
 Instruction {opcode: 0x96, name: "STX", size: 2, cycles: 4, page_cycles: 0, addressing: AddressingMode::ZeroPageY,},
@@ -181,11 +181,11 @@ Instruction {opcode: 0x97, name: "SAX", size: 2, cycles: 4, page_cycles: 0, addr
 Instruction {opcode: 0x98, name: "TYA", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Implied,},
 Instruction {opcode: 0x99, name: "STA", size: 3, cycles: 5, page_cycles: 0, addressing: AddressingMode::AbsoluteY,},
 Instruction {opcode: 0x9A, name: "TXS", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Implied,},
-Instruction {opcode: 0x9B, name: "TAS", size: 0, cycles: 5, page_cycles: 0, addressing: AddressingMode::AbsoluteY,},
-Instruction {opcode: 0x9C, name: "SHY", size: 0, cycles: 5, page_cycles: 0, addressing: AddressingMode::AbsoluteX,},
+Instruction {opcode: 0x9B, name: "TAS", size: 3, cycles: 5, page_cycles: 0, addressing: AddressingMode::AbsoluteY,},
+Instruction {opcode: 0x9C, name: "SHY", size: 3, cycles: 5, page_cycles: 0, addressing: AddressingMode::AbsoluteX,},
 Instruction {opcode: 0x9D, name: "STA", size: 3, cycles: 5, page_cycles: 0, addressing: AddressingMode::AbsoluteX,},
-Instruction {opcode: 0x9E, name: "SHX", size: 0, cycles: 5, page_cycles: 0, addressing: AddressingMode::AbsoluteY,},
-Instruction {opcode: 0x9F, name: "AHX", size: 0, cycles: 5, page_cycles: 0, addressing: AddressingMode::AbsoluteY,},
+Instruction {opcode: 0x9E, name: "SHX", size: 3, cycles: 5, page_cycles: 0, addressing: AddressingMode::AbsoluteY,},
+Instruction {opcode: 0x9F, name: "AHX", size: 3, cycles: 5, page_cycles: 0, addressing: AddressingMode::AbsoluteY,},
 Instruction {opcode: 0xA0, name: "LDY", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0xA1, name: "LDA", size: 2, cycles: 6, page_cycles: 0, addressing: AddressingMode::IndexedIndirect,},
 Instruction {opcode: 0xA2, name: "LDX", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
@@ -197,7 +197,7 @@ Instruction {opcode: 0xA7, name: "LAX", size: 2, cycles: 3, page_cycles: 0, addr
 Instruction {opcode: 0xA8, name: "TAY", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Implied,},
 Instruction {opcode: 0xA9, name: "LDA", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0xAA, name: "TAX", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Implied,},
-Instruction {opcode: 0xAB, name: "LAX", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
+Instruction {opcode: 0xAB, name: "LAX", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0xAC, name: "LDY", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0xAD, name: "LDA", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0xAE, name: "LDX", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
@@ -213,14 +213,14 @@ Instruction {opcode: 0xB7, name: "LAX", size: 2, cycles: 4, page_cycles: 0, addr
 Instruction {opcode: 0xB8, name: "CLV", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Implied,},
 Instruction {opcode: 0xB9, name: "LDA", size: 3, cycles: 4, page_cycles: 1, addressing: AddressingMode::AbsoluteY,},
 Instruction {opcode: 0xBA, name: "TSX", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Implied,},
-Instruction {opcode: 0xBB, name: "LAS", size: 0, cycles: 4, page_cycles: 1, addressing: AddressingMode::AbsoluteY,},
+Instruction {opcode: 0xBB, name: "LAS", size: 3, cycles: 4, page_cycles: 1, addressing: AddressingMode::AbsoluteY,},
 Instruction {opcode: 0xBC, name: "LDY", size: 3, cycles: 4, page_cycles: 1, addressing: AddressingMode::AbsoluteX,},
 Instruction {opcode: 0xBD, name: "LDA", size: 3, cycles: 4, page_cycles: 1, addressing: AddressingMode::AbsoluteX,},
 Instruction {opcode: 0xBE, name: "LDX", size: 3, cycles: 4, page_cycles: 1, addressing: AddressingMode::AbsoluteY,},
 Instruction {opcode: 0xBF, name: "LAX", size: 3, cycles: 4, page_cycles: 1, addressing: AddressingMode::AbsoluteY,},
 Instruction {opcode: 0xC0, name: "CPY", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0xC1, name: "CMP", size: 2, cycles: 6, page_cycles: 0, addressing: AddressingMode::IndexedIndirect,},
-Instruction {opcode: 0xC2, name: "NOP", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
+Instruction {opcode: 0xC2, name: "NOP", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0xC3, name: "DCP", size: 2, cycles: 8, page_cycles: 0, addressing: AddressingMode::IndexedIndirect,},
 Instruction {opcode: 0xC4, name: "CPY", size: 2, cycles: 3, page_cycles: 0, addressing: AddressingMode::ZeroPage,},
 Instruction {opcode: 0xC5, name: "CMP", size: 2, cycles: 3, page_cycles: 0, addressing: AddressingMode::ZeroPage,},
@@ -229,7 +229,7 @@ Instruction {opcode: 0xC7, name: "DCP", size: 2, cycles: 5, page_cycles: 0, addr
 Instruction {opcode: 0xC8, name: "INY", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Implied,},
 Instruction {opcode: 0xC9, name: "CMP", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0xCA, name: "DEX", size: 1, cycles: 2, page_cycles: 0, addressing: AddressingMode::Implied,},
-Instruction {opcode: 0xCB, name: "AXS", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
+Instruction {opcode: 0xCB, name: "AXS", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0xCC, name: "CPY", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0xCD, name: "CMP", size: 3, cycles: 4, page_cycles: 0, addressing: AddressingMode::Absolute,},
 Instruction {opcode: 0xCE, name: "DEC", size: 3, cycles: 6, page_cycles: 0, addressing: AddressingMode::Absolute,},
@@ -252,7 +252,7 @@ Instruction {opcode: 0xDE, name: "DEC", size: 3, cycles: 7, page_cycles: 0, addr
 Instruction {opcode: 0xDF, name: "DCP", size: 3, cycles: 7, page_cycles: 0, addressing: AddressingMode::AbsoluteX,},
 Instruction {opcode: 0xE0, name: "CPX", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0xE1, name: "SBC", size: 2, cycles: 6, page_cycles: 0, addressing: AddressingMode::IndexedIndirect,},
-Instruction {opcode: 0xE2, name: "NOP", size: 0, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
+Instruction {opcode: 0xE2, name: "NOP", size: 2, cycles: 2, page_cycles: 0, addressing: AddressingMode::Immediate,},
 Instruction {opcode: 0xE3, name: "ISC", size: 2, cycles: 8, page_cycles: 0, addressing: AddressingMode::IndexedIndirect,},
 Instruction {opcode: 0xE4, name: "CPX", size: 2, cycles: 3, page_cycles: 0, addressing: AddressingMode::ZeroPage,},
 Instruction {opcode: 0xE5, name: "SBC", size: 2, cycles: 3, page_cycles: 0, addressing: AddressingMode::ZeroPage,},
@@ -1,52 +1,64 @@
-use super::super::memory::Memory;
-use super::cpu::{Info, CPU};
+use super::bus::Bus;
+use super::cpu::{Info, StopReason, CPU};
 use super::description::AddressingMode;
 
-pub const IMPLEMENTATIONS: &'static [fn(&mut CPU, &Info); 256] = &[
-    CPU::brk, CPU::ora, CPU::upl, CPU::slo, CPU::nop, CPU::ora, CPU::asl, CPU::slo, // 0x00
-    CPU::php, CPU::ora, CPU::asl, CPU::upl, CPU::nop, CPU::ora, CPU::asl, CPU::slo, // 0x08
-    CPU::bpl, CPU::ora, CPU::upl, CPU::slo, CPU::nop, CPU::ora, CPU::asl, CPU::slo, // 0x10
-    CPU::clc, CPU::ora, CPU::nop, CPU::slo, CPU::nop, CPU::ora, CPU::asl, CPU::slo, // 0x18
-    CPU::jsr, CPU::and, CPU::upl, CPU::rla, CPU::bit, CPU::and, CPU::rol, CPU::rla, // 0x20
-    CPU::plp, CPU::and, CPU::rol, CPU::upl, CPU::bit, CPU::and, CPU::rol, CPU::rla, // 0x28
-    CPU::bmi, CPU::and, CPU::upl, CPU::rla, CPU::nop, CPU::and, CPU::rol, CPU::rla, // 0x30
-    CPU::sec, CPU::and, CPU::nop, CPU::rla, CPU::nop, CPU::and, CPU::rol, CPU::rla, // 0x38
-    CPU::rti, CPU::eor, CPU::upl, CPU::sre, CPU::nop, CPU::eor, CPU::lsr, CPU::sre, // 0x40
-    CPU::pha, CPU::eor, CPU::lsr, CPU::upl, CPU::jmp, CPU::eor, CPU::lsr, CPU::sre, // 0x48
-    CPU::bvc, CPU::eor, CPU::upl, CPU::sre, CPU::nop, CPU::eor, CPU::lsr, CPU::sre, // 0x50
-    CPU::cli, CPU::eor, CPU::nop, CPU::sre, CPU::nop, CPU::eor, CPU::lsr, CPU::sre, // 0x58
-    CPU::rts, CPU::adc, CPU::upl, CPU::rra, CPU::nop, CPU::adc, CPU::ror, CPU::rra, // 0x60
-    CPU::pla, CPU::adc, CPU::ror, CPU::upl, CPU::jmp, CPU::adc, CPU::ror, CPU::rra, // 0x68
-    CPU::bvs, CPU::adc, CPU::upl, CPU::rra, CPU::nop, CPU::adc, CPU::ror, CPU::rra, // 0x70
-    CPU::sei, CPU::adc, CPU::nop, CPU::rra, CPU::nop, CPU::adc, CPU::ror, CPU::rra, // 0x78
-    CPU::nop, CPU::sta, CPU::nop, CPU::sax, CPU::sty, CPU::sta, CPU::stx, CPU::sax, // 0x80
-    CPU::dey, CPU::upl, CPU::txa, CPU::upl, CPU::sty, CPU::sta, CPU::stx, CPU::sax, // 0x88
-    CPU::bcc, CPU::sta, CPU::upl, CPU::upl, CPU::sty, CPU::sta, CPU::stx, CPU::sax, // 0x90
-    CPU::tya, CPU::sta, CPU::txs, CPU::upl, CPU::upl, CPU::sta, CPU::upl, CPU::upl, // 0x98
-    CPU::ldy, CPU::lda, CPU::ldx, CPU::lax, CPU::ldy, CPU::lda, CPU::ldx, CPU::lax, // 0xA0
-    CPU::tay, CPU::lda, CPU::tax, CPU::upl, CPU::ldy, CPU::lda, CPU::ldx, CPU::lax, // 0xA8
-    CPU::bcs, CPU::lda, CPU::upl, CPU::lax, CPU::ldy, CPU::lda, CPU::ldx, CPU::lax, // 0xB0
-    CPU::clv, CPU::lda, CPU::tsx, CPU::upl, CPU::ldy, CPU::lda, CPU::ldx, CPU::lax, // 0xB8
-    CPU::cpy, CPU::cmp, CPU::upl, CPU::dcp, CPU::cpy, CPU::cmp, CPU::dec, CPU::dcp, // 0xC0
-    CPU::iny, CPU::cmp, CPU::dex, CPU::upl, CPU::cpy, CPU::cmp, CPU::dec, CPU::dcp, // 0xC8
-    CPU::bne, CPU::cmp, CPU::upl, CPU::dcp, CPU::nop, CPU::cmp, CPU::dec, CPU::dcp, // 0xD0
-    CPU::cld, CPU::cmp, CPU::nop, CPU::dcp, CPU::nop, CPU::cmp, CPU::dec, CPU::dcp, // 0xD8
-    CPU::cpx, CPU::sbc, CPU::upl, CPU::isc, CPU::cpx, CPU::sbc, CPU::inc, CPU::isc, // 0xE0
-    CPU::inx, CPU::sbc, CPU::nop, CPU::sbc, CPU::cpx, CPU::sbc, CPU::inc, CPU::isc, // 0xE8
-    CPU::beq, CPU::sbc, CPU::upl, CPU::isc, CPU::nop, CPU::sbc, CPU::inc, CPU::isc, // 0xF0
-    CPU::sed, CPU::sbc, CPU::nop, CPU::isc, CPU::nop, CPU::sbc, CPU::inc, CPU::isc, // 0xF8
-];
-
-impl CPU {
-	fn upl(&mut self, info: &Info) {
-		panic!(
-			"Unimplemented instruction op: 0x{:02X?}, {:?}",
-			info.i.opcode, info
-		);
-	}
-
-	// NOP - No Operation
-	fn nop(&mut self, _info: &Info) {}
+impl<B: Bus> CPU<B> {
+    // Instruction handlers indexed by opcode
+    pub const IMPLEMENTATIONS: [fn(&mut CPU<B>, &Info); 256] = [
+        CPU::brk, CPU::ora, CPU::kil, CPU::slo, CPU::nop, CPU::ora, CPU::asl, CPU::slo, // 0x00
+        CPU::php, CPU::ora, CPU::asl, CPU::anc, CPU::nop, CPU::ora, CPU::asl, CPU::slo, // 0x08
+        CPU::bpl, CPU::ora, CPU::kil, CPU::slo, CPU::nop, CPU::ora, CPU::asl, CPU::slo, // 0x10
+        CPU::clc, CPU::ora, CPU::nop, CPU::slo, CPU::nop, CPU::ora, CPU::asl, CPU::slo, // 0x18
+        CPU::jsr, CPU::and, CPU::kil, CPU::rla, CPU::bit, CPU::and, CPU::rol, CPU::rla, // 0x20
+        CPU::plp, CPU::and, CPU::rol, CPU::anc, CPU::bit, CPU::and, CPU::rol, CPU::rla, // 0x28
+        CPU::bmi, CPU::and, CPU::kil, CPU::rla, CPU::nop, CPU::and, CPU::rol, CPU::rla, // 0x30
+        CPU::sec, CPU::and, CPU::nop, CPU::rla, CPU::nop, CPU::and, CPU::rol, CPU::rla, // 0x38
+        CPU::rti, CPU::eor, CPU::kil, CPU::sre, CPU::nop, CPU::eor, CPU::lsr, CPU::sre, // 0x40
+        CPU::pha, CPU::eor, CPU::lsr, CPU::alr, CPU::jmp, CPU::eor, CPU::lsr, CPU::sre, // 0x48
+        CPU::bvc, CPU::eor, CPU::kil, CPU::sre, CPU::nop, CPU::eor, CPU::lsr, CPU::sre, // 0x50
+        CPU::cli, CPU::eor, CPU::nop, CPU::sre, CPU::nop, CPU::eor, CPU::lsr, CPU::sre, // 0x58
+        CPU::rts, CPU::adc, CPU::kil, CPU::rra, CPU::nop, CPU::adc, CPU::ror, CPU::rra, // 0x60
+        CPU::pla, CPU::adc, CPU::ror, CPU::arr, CPU::jmp, CPU::adc, CPU::ror, CPU::rra, // 0x68
+        CPU::bvs, CPU::adc, CPU::kil, CPU::rra, CPU::nop, CPU::adc, CPU::ror, CPU::rra, // 0x70
+        CPU::sei, CPU::adc, CPU::nop, CPU::rra, CPU::nop, CPU::adc, CPU::ror, CPU::rra, // 0x78
+        CPU::nop, CPU::sta, CPU::nop, CPU::sax, CPU::sty, CPU::sta, CPU::stx, CPU::sax, // 0x80
+        CPU::dey, CPU::nop, CPU::txa, CPU::xaa, CPU::sty, CPU::sta, CPU::stx, CPU::sax, // 0x88
+        CPU::bcc, CPU::sta, CPU::kil, CPU::ahx, CPU::sty, CPU::sta, CPU::stx, CPU::sax, // 0x90
+        CPU::tya, CPU::sta, CPU::txs, CPU::tas, CPU::shy, CPU::sta, CPU::shx, CPU::ahx, // 0x98
+        CPU::ldy, CPU::lda, CPU::ldx, CPU::lax, CPU::ldy, CPU::lda, CPU::ldx, CPU::lax, // 0xA0
+        CPU::tay, CPU::lda, CPU::tax, CPU::lxa, CPU::ldy, CPU::lda, CPU::ldx, CPU::lax, // 0xA8
+        CPU::bcs, CPU::lda, CPU::kil, CPU::lax, CPU::ldy, CPU::lda, CPU::ldx, CPU::lax, // 0xB0
+        CPU::clv, CPU::lda, CPU::tsx, CPU::las, CPU::ldy, CPU::lda, CPU::ldx, CPU::lax, // 0xB8
+        CPU::cpy, CPU::cmp, CPU::nop, CPU::dcp, CPU::cpy, CPU::cmp, CPU::dec, CPU::dcp, // 0xC0
+        CPU::iny, CPU::cmp, CPU::dex, CPU::axs, CPU::cpy, CPU::cmp, CPU::dec, CPU::dcp, // 0xC8
+        CPU::bne, CPU::cmp, CPU::kil, CPU::dcp, CPU::nop, CPU::cmp, CPU::dec, CPU::dcp, // 0xD0
+        CPU::cld, CPU::cmp, CPU::nop, CPU::dcp, CPU::nop, CPU::cmp, CPU::dec, CPU::dcp, // 0xD8
+        CPU::cpx, CPU::sbc, CPU::nop, CPU::isc, CPU::cpx, CPU::sbc, CPU::inc, CPU::isc, // 0xE0
+        CPU::inx, CPU::sbc, CPU::nop, CPU::sbc, CPU::cpx, CPU::sbc, CPU::inc, CPU::isc, // 0xE8
+        CPU::beq, CPU::sbc, CPU::kil, CPU::isc, CPU::nop, CPU::sbc, CPU::inc, CPU::isc, // 0xF0
+        CPU::sed, CPU::sbc, CPU::nop, CPU::isc, CPU::nop, CPU::sbc, CPU::inc, CPU::isc, // 0xF8
+    ];
+}
+
+impl<B: Bus> CPU<B> {
+	// KIL - Locks up the CPU, only reset brings it back.
+	// Size is 0, so PC stays on the opcode.
+	fn kil(&mut self, info: &Info) {
+		self.halt(StopReason::Jammed {
+			pc: info.pc,
+			opcode: info.i.opcode,
+		});
+	}
+
+	// NOP - No Operation, unofficial variants read their operand
+	fn nop(&mut self, info: &Info) {
+		match info.mode {
+			AddressingMode::Implied => {}
+			_ => {
+				self.read(info.address);
+			}
+		}
+	}
 
 	// SEI - Set Interrupt Disable
 	fn sei(&mut self, _info: &Info) {
@@ -115,17 +127,9 @@ impl CPU {
 	// LSR - Logical Shift Right
 	fn lsr(&mut self, info: &Info) {
 		match info.mode {
-			AddressingMode::Accumulator => {
-				self.c = self.a & 1;
-				self.a >>= 1;
-				self.set_zn(self.a);
-			}
+			AddressingMode::Accumulator => self.a = self.shift_right(self.a),
 			_ => {
-				let mut value = self.read(info.address);
-				self.c = value & 1;
-				value >>= 1;
-				self.write(info.address, value);
-				self.set_zn(value);
+				self.modify(info, CPU::shift_right);
 			}
 		}
 	}
@@ -138,6 +142,7 @@ impl CPU {
 
 	// PLA - Pull Accumulator
 	fn pla(&mut self, _info: &Info) {
+		self.read_stack();
 		self.a = self.pop();
 		self.set_zn(self.a);
 	}
@@ -151,9 +156,13 @@ impl CPU {
 	}
 
 	// JSR - Jump to Subroutine
+	// Only the low byte of the target is fetched before the return
+	// address is pushed, the high byte is read last
 	fn jsr(&mut self, info: &Info) {
+		self.read_stack();
 		self.push_word(self.pc.wrapping_sub(1));
-		self.pc = info.address;
+		let hi = self.read(self.pc.wrapping_sub(1)) as u16;
+		self.pc = hi << 8 | info.address;
 	}
 
 	// LDY - Load Y Register
@@ -180,9 +189,7 @@ impl CPU {
 
 	// INC - Increment Memory
 	fn inc(&mut self, info: &Info) {
-		let value = self.read(info.address).wrapping_add(1);
-		self.write(info.address, value);
-		self.set_zn(value);
+		self.modify(info, CPU::increment);
 	}
 
 	// TAX - Transfer Accumulator to X
@@ -205,13 +212,18 @@ impl CPU {
 
 	// RTS - Return from Subroutine
 	fn rts(&mut self, _info: &Info) {
-		self.pc = self.pop_word().wrapping_add(1);
+		self.read_stack();
+		self.pc = self.pop_word();
+		// return address points to last byte of JSR
+		self.read(self.pc);
+		self.pc = self.pc.wrapping_add(1);
 	}
 
 	// SLO - ASL -> ORA
 	fn slo(&mut self, info: &Info) {
-		self.asl(info);
-		self.ora(info);
+		let value = self.modify(info, CPU::shift_left);
+		self.a |= value;
+		self.set_zn(self.a);
 	}
 
 	// PHP - Push Processor Status
@@ -270,19 +282,16 @@ impl CPU {
 	}
 
 	// BRK - Force Interrupt
-	fn brk(&mut self, info: &Info) {
-		self.push_word(self.pc);
-		// self.php(info);
-		self.push(self.get_flags() | 0x30);
-		self.sei(info);
-		self.pc = self.mem.read_word(0xFFFE);
+	fn brk(&mut self, _info: &Info) {
+		// second byte is padding, skipped on return
+		self.interrupt(self.pc.wrapping_add(1), true);
 	}
 
 	// RTI - Return from Interrupt
-	fn rti(&mut self, info: &Info) {
-		//let value = self.pop();
-		//self.set_flags(value & 0xEF | 0x20);
-		self.plp(info);
+	fn rti(&mut self, _info: &Info) {
+		self.read_stack();
+		let value = self.pop();
+		self.set_flags(value & 0xEF | 0x20);
 		self.pc = self.pop_word();
 	}
 
@@ -299,6 +308,7 @@ impl CPU {
 
 	// PLP - Pull Processor Status
 	fn plp(&mut self, _info: &Info) {
+		self.read_stack();
 		let value = self.pop();
 		self.set_flags(value & 0xEF | 0x20);
 		// self.set_flags(value | 0x30);
@@ -315,19 +325,9 @@ impl CPU {
 	// ROL - Rotate Left
 	fn rol(&mut self, info: &Info) {
 		match info.mode {
-			AddressingMode::Accumulator => {
-				let c = self.c;
-				self.c = (self.a >> 7) & 1;
-				self.a = (self.a << 1) | c;
-				self.set_zn(self.a);
-			}
+			AddressingMode::Accumulator => self.a = self.rotate_left(self.a),
 			_ => {
-				let c = self.c;
-				let mut value = self.read(info.address);
-				self.c = (value >> 7) & 1;
-				value = (value << 1) | c;
-				self.write(info.address, value);
-				self.set_zn(value);
+				self.modify(info, CPU::rotate_left);
 			}
 		}
 	}
@@ -335,19 +335,9 @@ impl CPU {
 	// ROR - Rotate Right
 	fn ror(&mut self, info: &Info) {
 		match info.mode {
-			AddressingMode::Accumulator => {
-				let c = self.c;
-				self.c = self.a & 1;
-				self.a = (self.a >> 1) | (c << 7);
-				self.set_zn(self.a);
-			}
+			AddressingMode::Accumulator => self.a = self.rotate_right(self.a),
 			_ => {
-				let c = self.c;
-				let mut value = self.read(info.address);
-				self.c = value & 1;
-				value = (value >> 1) | (c << 7);
-				self.write(info.address, value);
-				self.set_zn(value);
+				self.modify(info, CPU::rotate_right);
 			}
 		}
 	}
@@ -360,8 +350,12 @@ impl CPU {
 
 	// ADC - Add with Carry
 	fn adc(&mut self, info: &Info) {
+		let value = self.read(info.address);
+		self.add(value);
+	}
+
+	fn add(&mut self, b: u8) {
 		let a = self.a;
-		let b = self.read(info.address);
 		let c = self.c;
 		self.a = a.wrapping_add(b).wrapping_add(c);
 		self.set_zn(self.a);
@@ -381,8 +375,8 @@ impl CPU {
 
 	// RRA - ROR -> ADC
 	fn rra(&mut self, info: &Info) {
-		self.ror(info);
-		self.adc(info);
+		let value = self.modify(info, CPU::rotate_right);
+		self.add(value);
 	}
 
 	// CLV - Clear Overflow Flag
@@ -410,8 +404,12 @@ impl CPU {
 
 	// SBC - Subtract with Carry
 	fn sbc(&mut self, info: &Info) {
+		let value = self.read(info.address);
+		self.subtract(value);
+	}
+
+	fn subtract(&mut self, b: u8) {
 		let a = self.a;
-		let b = self.read(info.address);
 		let c = self.c;
 		self.a = a.wrapping_sub(b).wrapping_sub(1u8.wrapping_sub(c));
 		self.set_zn(self.a);
@@ -456,26 +454,16 @@ impl CPU {
 	// ASL - Arithmetic Shift Left
 	fn asl(&mut self, info: &Info) {
 		match info.mode {
-			AddressingMode::Accumulator => {
-				self.c = (self.a >> 7) & 1;
-				self.a <<= 1;
-				self.set_zn(self.a);
-			}
+			AddressingMode::Accumulator => self.a = self.shift_left(self.a),
 			_ => {
-				let mut value = self.read(info.address);
-				self.c = (value >> 7) & 1;
-				value <<= 1;
-				self.write(info.address, value);
-				self.set_zn(value);
+				self.modify(info, CPU::shift_left);
 			}
 		}
 	}
 
 	// DEC - Decrement Memory
 	fn dec(&mut self, info: &Info) {
-		let value = self.read(info.address).wrapping_sub(1);
-		self.write(info.address, value);
-		self.set_zn(value);
+		self.modify(info, CPU::decrement);
 	}
 
 	// LAX - LDA -> TAX
@@ -486,37 +474,190 @@ impl CPU {
 
 	// SAX - Stores the bitwise AND of A and X
 	fn sax(&mut self, info: &Info) {
-		self.sta(info);
-		self.stx(info);
 		self.write(info.address, self.a & self.x);
 	}
 
 	// DCP - DEC -> CMP
 	fn dcp(&mut self, info: &Info) {
-		self.dec(info);
-		self.cmp(info);
+		let value = self.modify(info, CPU::decrement);
+		self.compare(self.a, value);
 	}
 
 	// ISC - INC -> SBC
 	fn isc(&mut self, info: &Info) {
-		self.inc(info);
-		self.sbc(info);
+		let value = self.modify(info, CPU::increment);
+		self.subtract(value);
 	}
 
 	// RLA - ROL -> AND
 	fn rla(&mut self, info: &Info) {
-		self.rol(info);
-		self.and(info);
+		let value = self.modify(info, CPU::rotate_left);
+		self.a &= value;
+		self.set_zn(self.a);
 	}
 
 	// SRE - LSR -> EOR
 	fn sre(&mut self, info: &Info) {
-		self.lsr(info);
-		self.eor(info);
+		let value = self.modify(info, CPU::shift_right);
+		self.a ^= value;
+		self.set_zn(self.a);
 	}
 
 	// CLI - Clear Interrupt Disable
 	fn cli(&mut self, _info: &Info) {
 		self.i = 0;
 	}
+
+	// ANC - AND, carry is copied from bit 7
+	fn anc(&mut self, info: &Info) {
+		self.and(info);
+		self.c = self.n;
+	}
+
+	// ALR - AND -> LSR A
+	fn alr(&mut self, info: &Info) {
+		self.and(info);
+		self.c = self.a & 1;
+		self.a >>= 1;
+		self.set_zn(self.a);
+	}
+
+	// ARR - AND -> ROR A, carry and overflow come from bits 6 and 5
+	fn arr(&mut self, info: &Info) {
+		self.and(info);
+		self.a = (self.a >> 1) | (self.c << 7);
+		self.set_zn(self.a);
+		self.c = (self.a >> 6) & 1;
+		self.v = self.c ^ ((self.a >> 5) & 1);
+	}
+
+	// AXS - X = A & X - operand, without borrow
+	fn axs(&mut self, info: &Info) {
+		let value = self.read(info.address);
+		let ax = self.a & self.x;
+		self.compare(ax, value);
+		self.x = ax.wrapping_sub(value);
+	}
+
+	// XAA - A = (A | magic) & X & operand. Unstable on real hardware,
+	// 0xEE is the most common magic constant
+	fn xaa(&mut self, info: &Info) {
+		let value = self.read(info.address);
+		self.a = (self.a | 0xEE) & self.x & value;
+		self.set_zn(self.a);
+	}
+
+	// LXA - A = X = (A | magic) & operand. Unstable as XAA,
+	// but the magic constant is 0xFF on 2A03
+	fn lxa(&mut self, info: &Info) {
+		let value = self.read(info.address);
+		self.a = (self.a | 0xFF) & value;
+		self.x = self.a;
+		self.set_zn(self.a);
+	}
+
+	// LAS - A = X = SP = memory & SP
+	fn las(&mut self, info: &Info) {
+		let value = self.read(info.address) & self.sp;
+		self.a = value;
+		self.x = value;
+		self.sp = value;
+		self.set_zn(value);
+	}
+
+	// TAS - SP = A & X, stores SP & (high byte of address + 1)
+	fn tas(&mut self, info: &Info) {
+		self.sp = self.a & self.x;
+		let index = self.y;
+		self.store_high_and(info, index, self.sp);
+	}
+
+	// SHY - Stores Y & (high byte of address + 1)
+	fn shy(&mut self, info: &Info) {
+		let index = self.x;
+		self.store_high_and(info, index, self.y);
+	}
+
+	// SHX - Stores X & (high byte of address + 1)
+	fn shx(&mut self, info: &Info) {
+		let index = self.y;
+		self.store_high_and(info, index, self.x);
+	}
+
+	// AHX - Stores A & X & (high byte of address + 1)
+	fn ahx(&mut self, info: &Info) {
+		let index = self.y;
+		self.store_high_and(info, index, self.a & self.x);
+	}
+
+	// Common part of unstable stores: value is ANDed with high byte of
+	// base address plus one. When indexing crosses a page the high byte
+	// of target address is replaced by the stored value.
+	fn store_high_and(&mut self, info: &Info, index: u8, value: u8) {
+		let base = info.address.wrapping_sub(index as u16);
+		let value = value & ((base >> 8) as u8).wrapping_add(1);
+		let address = if base & 0xFF00 != info.address & 0xFF00 {
+			(value as u16) << 8 | info.address & 0xFF
+		} else {
+			info.address
+		};
+		self.write(address, value);
+	}
+
+	// Read-modify-write instructions write the unmodified value back
+	// while modifying it, then write the result
+	fn modify(&mut self, info: &Info, f: fn(&mut CPU<B>, u8) -> u8) -> u8 {
+		let value = self.read(info.address);
+		self.write(info.address, value);
+		let value = f(self, value);
+		self.write(info.address, value);
+		value
+	}
+
+	// Stack pull takes a cycle to increment SP, reading the stack meanwhile
+	fn read_stack(&mut self) {
+		self.read(self.sp as u16 | 0x100);
+	}
+
+	fn shift_left(&mut self, value: u8) -> u8 {
+		self.c = (value >> 7) & 1;
+		let value = value << 1;
+		self.set_zn(value);
+		value
+	}
+
+	fn shift_right(&mut self, value: u8) -> u8 {
+		self.c = value & 1;
+		let value = value >> 1;
+		self.set_zn(value);
+		value
+	}
+
+	fn rotate_left(&mut self, value: u8) -> u8 {
+		let c = self.c;
+		self.c = (value >> 7) & 1;
+		let value = (value << 1) | c;
+		self.set_zn(value);
+		value
+	}
+
+	fn rotate_right(&mut self, value: u8) -> u8 {
+		let c = self.c;
+		self.c = value & 1;
+		let value = (value >> 1) | (c << 7);
+		self.set_zn(value);
+		value
+	}
+
+	fn increment(&mut self, value: u8) -> u8 {
+		let value = value.wrapping_add(1);
+		self.set_zn(value);
+		value
+	}
+
+	fn decrement(&mut self, value: u8) -> u8 {
+		let value = value.wrapping_sub(1);
+		self.set_zn(value);
+		value
+	}
 }
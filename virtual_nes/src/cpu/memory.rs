@@ -1,8 +1,9 @@
 use super::super::apu::APU;
 use super::super::controller::Controller;
 use super::super::mapper::Mapper;
-use super::super::memory::Memory;
 use super::super::ppu::PPU;
+use super::super::region::Region;
+use super::bus::Bus;
 use std::rc::Rc;
 use std::cell::RefCell;
 
@@ -14,6 +15,17 @@ pub struct CpuMemory {
     pub apu: APU,
     pub controller_1: Controller,
     pub controller_2: Controller,
+    pub bus_faults: bool,       // debugging, unmapped accesses stop CPU
+    fault: Option<u16>,         // first unmapped address since last check
+    dots_ratio: (usize, usize), // PPU dots per CPU cycles
+    dot_remainder: usize,       // fractional PPU dots left over from previous cycle
+    cycles: u64,
+    dma_page: Option<u8>, // OAM DMA waiting for CPU to halt
+    dma_address: u16,     // next byte copied by OAM DMA
+    dma_cycles: usize,    // OAM DMA cycles left
+    dma_pause: usize,     // OAM DMA cycles taken over by DMC
+    dma_value: u8,
+    stall: usize,
 }
 
 impl CpuMemory {
@@ -31,30 +43,82 @@ impl CpuMemory {
             apu: apu,
             controller_1: controller_1,
             controller_2: controller_2,
+            bus_faults: false,
+            fault: None,
+            dots_ratio: Region::Ntsc.ppu_dots_ratio(),
+            dot_remainder: 0,
+            cycles: 0,
+            dma_page: None,
+            dma_address: 0,
+            dma_cycles: 0,
+            dma_pause: 0,
+            dma_value: 0,
+            stall: 0,
         }
     }
 
-    // Bug in 6502: low byte wrapping without high increment
-    pub fn read_word_bug(&self, address: u16) -> u16 {
-        let addr_hi = (address & 0xFF00) | (address + 1 & 0xFF);
-        let lo = self.read(address) as u16;
-        let hi = self.read(addr_hi) as u16;
-        (hi << 8) | lo
+    pub fn set_region(&mut self, region: Region) {
+        self.dots_ratio = region.ppu_dots_ratio();
+        self.dot_remainder = 0;
+        self.ppu.set_region(region);
+        self.apu.set_region(region);
+    }
+
+    fn set_fault(&mut self, address: u16) {
+        if self.bus_faults && self.fault.is_none() {
+            self.fault = Some(address);
+        }
+    }
+
+    // OAM DMA copies page $XX00-$XXFF to PPU OAM through OAMDATA.
+    // Reads happen on even cycles, so the DMA takes one more cycle
+    // to align if CPU is halted on an even cycle.
+    fn start_oam_dma(&mut self, page: u8) {
+        self.dma_address = (page as u16) << 8;
+        self.dma_cycles = 513 + self.cycles.is_multiple_of(2) as usize;
+        self.stall += self.dma_cycles;
+    }
+
+    // DMA alternates between reading a byte and writing it to OAMDATA,
+    // the last cycle belongs to the halted CPU read
+    fn step_oam_dma(&mut self) {
+        if self.dma_cycles == 0 {
+            return;
+        }
+        if self.dma_pause > 0 {
+            self.dma_pause -= 1;
+            return;
+        }
+
+        self.dma_cycles -= 1;
+        if self.dma_cycles == 0 || self.dma_cycles > 512 {
+            return;
+        }
+        if self.dma_cycles.is_multiple_of(2) {
+            let address = self.dma_address;
+            self.dma_value = self.read(address);
+            self.dma_address = address.wrapping_add(1);
+        } else {
+            self.ppu.write_register(0x2004, self.dma_value);
+        }
     }
 }
 
-impl Memory for CpuMemory {
-    fn read(&self, address: u16) -> u8 {
+impl Bus for CpuMemory {
+    // Controller, PPU and APU needs to be mutable while reading
+    fn read(&mut self, address: u16) -> u8 {
         match address {
-            0x0000..0x2000 => self.ram[address as usize % 0x0800],
-            // 0x2000..0x4000 => self.ppu.read_register(0x2000 + address % 8),
-            // 0x4014 => self.ppu.read_register(address),
+            0x2000..0x4000 => self.ppu.read_register(0x2000 + address % 8),
             0x4015 => self.apu.read_register(address),
-            // Controllers needs mutable reference
-            // 0x4016 => self.controller_1.read(),
-            // 0x4017 => self.controller_2.read(),
-            0x6000...0xFFFF => self.mapper.borrow().read(address),
-            _ => panic!("unhandled cpu memory read at address: 0x{:04X?}", address),
+            0x4016 => self.controller_1.read(),
+            0x4017 => self.controller_2.read(),
+            // OAMDMA is write only, CPU test registers at $4018-$401F
+            // are disabled and most boards leave $4020-$5FFF unmapped
+            0x4014 | 0x4018..0x6000 => {
+                self.set_fault(address);
+                (address >> 8) as u8
+            }
+            _ => self.peek(address),
         }
     }
 
@@ -63,15 +127,78 @@ impl Memory for CpuMemory {
             0x0000..0x2000 => self.ram[address as usize % 0x0800] = value,
             0x2000..0x4000 => self.ppu.write_register(0x2000 + address % 8, value),
             0x4000..0x4014 => self.apu.write_register(address, value),
-            // 0x4014 => self.ppu.write_register(address, value),
+            0x4014 => self.dma_page = Some(value),
             0x4015 => self.apu.write_register(address, value),
             0x4016 => {
                 self.controller_1.write(value);
                 self.controller_2.write(value)
             }
             0x4017 => self.apu.write_register(address, value),
-            0x6000...0xFFFF => self.mapper.borrow_mut().write(address, value),
-            _ => panic!("unhandled cpu memory write at address: 0x{:04X?}", address),
+            0x4020..0x6000 => self.mapper.borrow_mut().write_expansion(address, value),
+            0x6000..=0xFFFF => self.mapper.borrow_mut().write(address, value),
+            // ignored unless debugging
+            _ => self.set_fault(address),
+        }
+    }
+
+    // Registers can't be read without side effects, open bus
+    // usually holds the high byte of the address
+    fn peek(&self, address: u16) -> u8 {
+        match address {
+            0x0000..0x2000 => self.ram[address as usize % 0x0800],
+            0x6000..=0xFFFF => self.mapper.borrow().read(address),
+            _ => (address >> 8) as u8,
         }
     }
+
+    // Advances PPU, APU, OAM DMA and cartridge by one CPU cycle,
+    // returns true if PPU triggered NMI
+    fn tick(&mut self) -> bool {
+        self.cycles += 1;
+
+        // PAL PPU runs 3.2 dots per CPU cycle
+        let (dots, cycles) = self.dots_ratio;
+        self.dot_remainder += dots;
+        let mut nmi = false;
+        while self.dot_remainder >= cycles {
+            self.dot_remainder -= cycles;
+            nmi |= self.ppu.step();
+        }
+
+        self.apu.step();
+        self.step_oam_dma();
+        self.mapper.borrow_mut().step();
+        nmi
+    }
+
+    // IRQ line is level triggered and shared, any of APU frame counter,
+    // DMC and cartridge holds it low
+    fn irq(&self) -> bool {
+        self.apu.frame_irq() || self.apu.dmc.irq || self.mapper.borrow().irq()
+    }
+
+    // OAM DMA and DMC sample fetches halt CPU on its read cycle
+    fn take_stall(&mut self) -> usize {
+        if let Some(page) = self.dma_page.take() {
+            self.start_oam_dma(page);
+        }
+        for _ in 0..self.apu.take_dma_requests() {
+            if self.dma_cycles > 0 {
+                // sample fetch takes over a read and write of OAM DMA
+                self.dma_pause += 2;
+                self.stall += 2;
+            } else {
+                // halt and dummy cycles, the fetch waits for an even cycle
+                self.stall += 3 + (self.cycles % 2) as usize;
+            }
+        }
+
+        let stall = self.stall;
+        self.stall = 0;
+        stall
+    }
+
+    fn take_fault(&mut self) -> Option<u16> {
+        self.fault.take()
+    }
 }
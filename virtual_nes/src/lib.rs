@@ -13,22 +13,39 @@
 #[macro_use]
 extern crate failure;
 
-mod cpu;
+/// Standalone 6502 core, generic over the bus it is connected to.
+pub mod cpu;
 mod apu;
 mod controller;
 mod mapper;
 mod memory;
+mod nsf;
 mod ppu;
+mod region;
 mod rom;
 mod virtual_console;
+mod wav;
 
+pub use self::apu::{Channel, OutputFilter};
+pub use self::cpu::StopReason;
+pub use self::nsf::{Nsf, NsfPlayer};
+pub use self::ppu::{Palette, SCREEN_HEIGHT, SCREEN_WIDTH};
+pub use self::region::Region;
 pub use self::virtual_console::VirtualConsole;
+pub use self::wav::{write_wav, BitDepth};
 
 #[cfg(test)]
 mod tests {
+    use super::apu::APU;
+    use super::controller::Controller;
+    use super::cpu::{Bus, CpuMemory, CPU};
+    use super::mapper::create_mapper;
+    use super::ppu::PPU;
     use super::rom::Rom;
     use failure::Error;
+    use std::cell::RefCell;
     use std::fs::File;
+    use std::rc::Rc;
 
     /// Helper function for loading test rom file.
     ///
@@ -39,4 +56,46 @@ mod tests {
         let mut f = File::open(format!("../roms/{}.nes", name))?;
         Ok(Rom::load(&mut f)?)
     }
+
+    /// Runs blargg's test ROM until it reports the result at $6000.
+    /// Returns the result code, 0 on success, and the text printed by the ROM.
+    pub fn run_test_rom(name: &str) -> Result<(u8, String), Error> {
+        let rom = create_rom(name)?;
+        let mapper = Rc::new(RefCell::new(create_mapper(rom)?));
+        let ppu = PPU::new(Rc::clone(&mapper));
+        let apu = APU::new(Rc::clone(&mapper));
+        let player_1 = Controller::new();
+        let player_2 = Controller::new();
+        let cpu_mem = CpuMemory::new(mapper, ppu, apu, player_1, player_2);
+        let mut cpu = CPU::new(cpu_mem);
+
+        cpu.reset();
+
+        // $6000 is 0x80 while running, result code when done
+        let mut running = false;
+        let code = loop {
+            match cpu.mem.peek(0x6000) {
+                0x80 => running = true,
+                code if running => break code,
+                _ => {}
+            }
+            cpu.step()?;
+        };
+
+        if cpu.mem.peek(0x6001) != 0xDE || cpu.mem.peek(0x6002) != 0xB0 || cpu.mem.peek(0x6003) != 0x61 {
+            return Err(format_err!("{} has no valid result signature", name));
+        }
+
+        let mut status = String::new();
+        let mut i = 0x6004;
+        loop {
+            let c = cpu.mem.peek(i);
+            if c == 0 {
+                break;
+            }
+            status.push(c as char);
+            i += 1;
+        }
+        Ok((code, status))
+    }
 }
@@ -17,6 +17,7 @@ pub struct Mapper1 {
     chr_bank_1: u8,
     prg_offsets: [usize; 2],
     chr_offsets: [usize; 2],
+    write_cycles: u8, // CPU cycles since last register write
 }
 
 impl Mapper1 {
@@ -34,6 +35,7 @@ impl Mapper1 {
             chr_bank_1: 0,
             prg_offsets: [0, prg_offset_1],
             chr_offsets: [0, 0],
+            write_cycles: 2,
         }
     }
 
@@ -68,9 +70,9 @@ impl Mapper1 {
 
     fn write_register(&mut self, address: u16, value: u8) {
         match address {
-            0x0000...0x9FFF => self.write_control(value),
-            0xA000...0xBFFF => self.write_chr_bank_0(value),
-            0xC000...0xDFFF => self.write_chr_bank_1(value),
+            0x0000..=0x9FFF => self.write_control(value),
+            0xA000..=0xBFFF => self.write_chr_bank_0(value),
+            0xC000..=0xDFFF => self.write_chr_bank_1(value),
             _ => self.write_prg_bank(value),
         }
     }
@@ -152,6 +154,20 @@ impl Mapper for Mapper1 {
     fn get_mirroring(&self) -> &Mirroring {
         &self.rom.mirroring
     }
+
+    fn read_vram(&self, address: usize) -> u8 {
+        self.rom.vram.get(address).cloned().unwrap_or(0)
+    }
+
+    fn write_vram(&mut self, address: usize, value: u8) {
+        if let Some(v) = self.rom.vram.get_mut(address) {
+            *v = value;
+        }
+    }
+
+    fn step(&mut self) {
+        self.write_cycles = self.write_cycles.saturating_add(1);
+    }
 }
 
 impl Memory for Mapper1 {
@@ -164,13 +180,14 @@ impl Memory for Mapper1 {
                 self.rom.chr[self.chr_offsets[bank] + offset]
             }
             0x6000..0x8000 => self.rom.ram[address - 0x6000],
-            0x8000...0xFFFF => {
+            0x8000..=0xFFFF => {
                 let address = address - 0x8000;
                 let bank = address / 0x4000;
                 let offset = address % 0x4000;
                 self.rom.prg[self.prg_offsets[bank] + offset]
             }
-            _ => panic!("Read at address: {:04x}", address),
+            // Open bus
+            _ => (address >> 8) as u8,
         }
     }
 
@@ -183,8 +200,16 @@ impl Memory for Mapper1 {
                 self.rom.chr[self.chr_offsets[bank] + offset] = value
             }
             0x6000..0x8000 => self.rom.ram[addr - 0x6000] = value,
-            0x8000...0xFFFF => self.load_register(address, value),
-            _ => panic!("Write at address: {:04x}", address),
+            0x8000..=0xFFFF => {
+                // Writes on consecutive cycles are ignored,
+                // e.g. the second write of INC/DEC
+                let consecutive = self.write_cycles < 2;
+                self.write_cycles = 0;
+                if !consecutive {
+                    self.load_register(address, value);
+                }
+            }
+            _ => {}
         }
     }
 }
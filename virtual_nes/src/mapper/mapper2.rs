@@ -28,6 +28,16 @@ impl Mapper for Mapper2 {
     fn get_mirroring(&self) -> &Mirroring {
         &self.rom.mirroring
     }
+
+    fn read_vram(&self, address: usize) -> u8 {
+        self.rom.vram.get(address).cloned().unwrap_or(0)
+    }
+
+    fn write_vram(&mut self, address: usize, value: u8) {
+        if let Some(v) = self.rom.vram.get_mut(address) {
+            *v = value;
+        }
+    }
 }
 
 impl Memory for Mapper2 {
@@ -37,8 +47,9 @@ impl Memory for Mapper2 {
             0x0000..0x2000 => self.rom.chr[address],
             0x6000..0x8000 => self.rom.ram[address - 0x6000],
             0x8000..0xC000 => self.rom.prg[self.current_bank * PRG_BANK_SIZE + address - 0x8000],
-            0xC000...0xFFFF => self.rom.prg[self.last_bank * PRG_BANK_SIZE + address - 0xC000],
-            _ => panic!("Read at address: {:04x}", address),
+            0xC000..=0xFFFF => self.rom.prg[self.last_bank * PRG_BANK_SIZE + address - 0xC000],
+            // Open bus
+            _ => (address >> 8) as u8,
         }
     }
 
@@ -47,8 +58,8 @@ impl Memory for Mapper2 {
         match address {
             0x0000..0x2000 => self.rom.chr[address] = value,
             0x6000..0x8000 => self.rom.ram[address - 0x6000] = value,
-            0x8000...0xFFFF => self.current_bank = value as usize % self.banks_count,
-            _ => panic!("Write at address: {:04x}", address),
+            0x8000..=0xFFFF => self.current_bank = value as usize % self.banks_count,
+            _ => {}
         }
     }
 }
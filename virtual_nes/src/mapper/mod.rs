@@ -10,6 +10,40 @@ use super::rom::{Mirroring, Rom};
 
 pub trait Mapper: Memory {
     fn get_mirroring(&self) -> &Mirroring;
+
+    // Cartridge nametable RAM for the third and fourth nametables
+    // of four-screen boards
+    fn read_vram(&self, _address: usize) -> u8 {
+        0
+    }
+
+    fn write_vram(&mut self, _address: usize, _value: u8) {}
+
+    // Writes to CPU space $4020-$5FFF, unmapped on most boards
+    fn write_expansion(&mut self, _address: u16, _value: u8) {}
+
+    // Called with every address the PPU puts on its bus, including
+    // rendering fetches, so boards can watch A12 to clock scanline counters
+    fn ppu_address(&mut self, _address: u16) {}
+
+    // Called once per CPU cycle
+    fn step(&mut self) {}
+
+    // Cartridge IRQ output, polled by the console
+    fn irq(&self) -> bool {
+        false
+    }
+
+    // Lets the board supply nametable bytes instead of CIRAM,
+    // None falls through to the regular mirroring
+    fn read_nametable(&mut self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    // Returns true if the write was handled by the board
+    fn write_nametable(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
 }
 
 pub fn create_mapper(rom: Rom) -> Result<Box<dyn Mapper>, Error> {
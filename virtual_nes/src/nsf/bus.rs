@@ -0,0 +1,75 @@
+use super::super::apu::APU;
+use super::super::cpu::Bus;
+use super::super::mapper::Mapper;
+use super::super::region::Region;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// CPU view of an NSF player: RAM, APU and the tune's banks.
+// Nothing else of the console is needed to play music.
+#[derive(Debug)]
+pub struct NsfBus {
+    pub ram: Vec<u8>,
+    pub mapper: Rc<RefCell<Box<dyn Mapper>>>,
+    pub apu: APU,
+    cycles: u64,
+}
+
+impl NsfBus {
+    pub fn new(mapper: Rc<RefCell<Box<dyn Mapper>>>, apu: APU) -> NsfBus {
+        NsfBus {
+            ram: vec![0; 2048],
+            mapper,
+            apu,
+            cycles: 0,
+        }
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.apu.set_region(region);
+    }
+}
+
+impl Bus for NsfBus {
+    fn read(&mut self, address: u16) -> u8 {
+        match address {
+            0x4015 => self.apu.read_register(address),
+            _ => self.peek(address),
+        }
+    }
+
+    // PPU and controller registers are ignored
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..0x2000 => self.ram[address as usize % 0x0800] = value,
+            0x4000..=0x4013 | 0x4015 | 0x4017 => self.apu.write_register(address, value),
+            0x4020..0x6000 => self.mapper.borrow_mut().write_expansion(address, value),
+            0x6000..=0xFFFF => self.mapper.borrow_mut().write(address, value),
+            _ => {}
+        }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        match address {
+            0x0000..0x2000 => self.ram[address as usize % 0x0800],
+            0x6000..=0xFFFF => self.mapper.borrow().read(address),
+            _ => (address >> 8) as u8,
+        }
+    }
+
+    fn tick(&mut self) -> bool {
+        self.cycles += 1;
+        self.apu.step();
+        self.mapper.borrow_mut().step();
+        false
+    }
+
+    fn irq(&self) -> bool {
+        self.apu.frame_irq() || self.apu.dmc.irq
+    }
+
+    // DMC sample fetches, same timing as on the console
+    fn take_stall(&mut self) -> usize {
+        self.apu.take_dma_requests() * (3 + (self.cycles % 2) as usize)
+    }
+}
@@ -0,0 +1,88 @@
+use super::super::mapper::Mapper;
+use super::super::memory::Memory;
+use super::super::rom::Mirroring;
+use super::nsf::Nsf;
+use failure::Error;
+
+const BANK_SIZE: usize = 4 * 1024;
+const RAM_SIZE: usize = 8 * 1024;
+
+// NSF memory map: 8 KiB RAM at $6000 and eight 4 KiB PRG slots at $8000
+// switched by writes to $5FF8-$5FFF
+#[derive(Debug)]
+pub struct NsfMapper {
+    prg: Vec<u8>,
+    ram: Vec<u8>,
+    banks: [usize; 8],
+    mirroring: Mirroring,
+}
+
+impl NsfMapper {
+    pub fn from_nsf(nsf: &Nsf) -> Result<Self, Error> {
+        // Data is placed so that banks line up with load address
+        let padding = match nsf.banks {
+            Some(_) => nsf.load_address as usize & 0x0FFF,
+            None if nsf.load_address >= 0x8000 => nsf.load_address as usize - 0x8000,
+            None => {
+                return Err(format_err!(
+                    "NSF load address 0x{:04X} is below 0x8000",
+                    nsf.load_address
+                ))
+            }
+        };
+
+        let mut prg = vec![0; padding];
+        prg.extend_from_slice(&nsf.data);
+        let banks = prg.len().div_ceil(BANK_SIZE).max(8);
+        prg.resize(banks * BANK_SIZE, 0);
+
+        // Same mapping as the player sets up before INIT
+        let mut slots = [0, 1, 2, 3, 4, 5, 6, 7];
+        if let Some(initial) = nsf.banks {
+            for (slot, bank) in slots.iter_mut().zip(initial.iter()) {
+                *slot = *bank as usize % banks;
+            }
+        }
+
+        Ok(NsfMapper {
+            prg: prg,
+            ram: vec![0; RAM_SIZE],
+            banks: slots,
+            mirroring: Mirroring::Horizontal,
+        })
+    }
+}
+
+impl Mapper for NsfMapper {
+    fn get_mirroring(&self) -> &Mirroring {
+        &self.mirroring
+    }
+
+    fn write_expansion(&mut self, address: u16, value: u8) {
+        if let 0x5FF8..=0x5FFF = address {
+            let banks = self.prg.len() / BANK_SIZE;
+            self.banks[address as usize - 0x5FF8] = value as usize % banks;
+        }
+    }
+}
+
+impl Memory for NsfMapper {
+    fn read(&self, address: u16) -> u8 {
+        let address = address as usize;
+        match address {
+            0x6000..0x8000 => self.ram[address - 0x6000],
+            0x8000..=0xFFFF => {
+                let slot = (address - 0x8000) / BANK_SIZE;
+                self.prg[self.banks[slot] * BANK_SIZE + address % BANK_SIZE]
+            }
+            // No CHR, PPU is never used
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if let 0x6000..0x8000 = address {
+            self.ram[address as usize - 0x6000] = value;
+        }
+    }
+}
@@ -0,0 +1,180 @@
+mod bus;
+mod mapper;
+mod nsf;
+mod player;
+
+pub use self::nsf::Nsf;
+pub use self::player::NsfPlayer;
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapper::Mapper;
+    use super::super::memory::Memory;
+    use super::super::region::Region;
+    use super::mapper::NsfMapper;
+    use super::*;
+    use std::io::Cursor;
+
+    // INIT stores A and X at $02 and $03 and starts a square wave,
+    // PLAY counts calls at $00
+    fn create_nsf() -> Vec<u8> {
+        let mut data = vec![0; 0x80];
+        data[..5].copy_from_slice(b"NESM\x1A");
+        data[0x05] = 1;
+        data[0x06] = 3;
+        data[0x07] = 2;
+        data[0x08..0x0E].copy_from_slice(&[0x00, 0x80, 0x00, 0x80, 0x00, 0x81]);
+        data[0x0E..0x13].copy_from_slice(b"Title");
+        data[0x2E..0x34].copy_from_slice(b"Artist");
+        data[0x6E..0x70].copy_from_slice(&[0x00, 0x00]);
+
+        let mut code = vec![
+            0x85, 0x02, // STA $02
+            0x86, 0x03, // STX $03
+            0xA9, 0x0F, 0x8D, 0x15, 0x40, // STA #$0F -> $4015
+            0xA9, 0xBF, 0x8D, 0x00, 0x40, // STA #$BF -> $4000
+            0xA9, 0xFD, 0x8D, 0x02, 0x40, // STA #$FD -> $4002
+            0xA9, 0x00, 0x8D, 0x03, 0x40, // STA #$00 -> $4003
+            0x60, // RTS
+        ];
+        code.resize(0x100, 0);
+        code.extend_from_slice(&[
+            0xE6, 0x00, // INC $00
+            0x60, // RTS
+        ]);
+        data.extend_from_slice(&code);
+        data
+    }
+
+    fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = vec![data.len() as u8, 0, 0, 0];
+        chunk.extend_from_slice(id);
+        chunk.extend_from_slice(data);
+        chunk
+    }
+
+    #[test]
+    fn load_nsf() {
+        let nsf = Nsf::load(&mut Cursor::new(create_nsf())).unwrap();
+        assert_eq!(nsf.title, "Title");
+        assert_eq!(nsf.artist, "Artist");
+        assert_eq!(nsf.songs, 3);
+        assert_eq!(nsf.start_song, 1);
+        assert_eq!(nsf.init_address, 0x8000);
+        assert_eq!(nsf.play_address, 0x8100);
+        assert_eq!(nsf.play_speed(Region::Ntsc), 16639);
+        assert!(nsf.banks.is_none());
+
+        assert!(Nsf::load(&mut Cursor::new(vec![0; 0x100])).is_err());
+    }
+
+    #[test]
+    fn load_nsfe() {
+        let mut data = b"NSFE".to_vec();
+        data.extend(chunk(
+            b"INFO",
+            &[0x00, 0x80, 0x00, 0x80, 0x00, 0x81, 0x01, 0x00, 0x02],
+        ));
+        data.extend(chunk(b"DATA", &[0x60; 16]));
+        data.extend(chunk(b"auth", b"Game\0Composer\0\0Ripper\0"));
+        data.extend(chunk(b"tlbl", b"Intro\0Ending\0"));
+        data.extend(chunk(b"time", &[0x10, 0x27, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]));
+        data.extend(chunk(b"plst", &[1, 0]));
+        data.extend(chunk(b"NEND", &[]));
+
+        let nsf = Nsf::load(&mut Cursor::new(data.clone())).unwrap();
+        assert_eq!(nsf.songs, 2);
+        assert_eq!(nsf.play_address, 0x8100);
+        assert_eq!(nsf.region, Region::Pal);
+        assert_eq!(nsf.play_speed(Region::Pal), 19997);
+        assert_eq!(nsf.title, "Game");
+        assert_eq!(nsf.artist, "Composer");
+        assert_eq!(nsf.copyright, "");
+        assert_eq!(nsf.ripper, "Ripper");
+        assert_eq!(nsf.track_labels, vec!["Intro", "Ending"]);
+        assert_eq!(nsf.track_times, vec![Some(10000), None]);
+        assert_eq!(nsf.playlist, vec![1, 0]);
+        assert_eq!(nsf.play_order(), vec![1, 0]);
+
+        // Unknown chunks are skipped unless required
+        let mut extra = data[..4].to_vec();
+        extra.extend(chunk(b"xtra", &[1, 2, 3]));
+        extra.extend_from_slice(&data[4..]);
+        assert!(Nsf::load(&mut Cursor::new(extra)).is_ok());
+        let mut extra = data[..4].to_vec();
+        extra.extend(chunk(b"XTRA", &[1, 2, 3]));
+        extra.extend_from_slice(&data[4..]);
+        assert!(Nsf::load(&mut Cursor::new(extra)).is_err());
+    }
+
+    #[test]
+    fn bankswitching() {
+        let mut nsf = Nsf::load(&mut Cursor::new(create_nsf())).unwrap();
+        nsf.load_address = 0x8010;
+        nsf.banks = Some([0, 1, 2, 1, 0, 0, 0, 2]);
+        nsf.data = (0..3u8)
+            .flat_map(|bank| vec![bank; 0x1000])
+            .skip(0x10)
+            .collect();
+        let mut mapper = NsfMapper::from_nsf(&nsf).unwrap();
+
+        // Data is padded to load address within the bank,
+        // slots start with the banks from the header
+        assert_eq!(mapper.read(0x8000), 0);
+        assert_eq!(mapper.read(0x8010), 0);
+        assert_eq!(mapper.read(0x9000), 1);
+        assert_eq!(mapper.read(0xA000), 2);
+        assert_eq!(mapper.read(0xB000), 1);
+        assert_eq!(mapper.read(0xF000), 2);
+        mapper.write_expansion(0x5FF8, 2);
+        mapper.write_expansion(0x5FFF, 1);
+        assert_eq!(mapper.read(0x8FFF), 2);
+        assert_eq!(mapper.read(0xF000), 1);
+
+        mapper.write(0x6000, 0x42);
+        assert_eq!(mapper.read(0x6000), 0x42);
+    }
+
+    #[test]
+    fn player() {
+        let nsf = Nsf::load(&mut Cursor::new(create_nsf())).unwrap();
+        let mut player = NsfPlayer::new(nsf).unwrap();
+        assert_eq!(player.get_track(), 1);
+
+        let samples = player.render_seconds(1.0).unwrap();
+        assert_eq!(samples.len(), 44_100);
+        assert!(samples.iter().any(|s| s.abs() > 0.05));
+        assert_eq!(player.read_memory(0x02), 1);
+        assert_eq!(player.read_memory(0x03), 0);
+        let calls = player.read_memory(0x00);
+        assert!((59..=61).contains(&calls));
+
+        // Starting a track resets memory
+        player.start_track(2).unwrap();
+        assert_eq!(player.read_memory(0x00), 0);
+        player.render(100).unwrap();
+        assert_eq!(player.read_memory(0x02), 2);
+        assert!(player.start_track(3).is_err());
+
+        // Without playlist tracks go in order
+        player.start_track(1).unwrap();
+        player.next_track().unwrap();
+        assert_eq!(player.get_track(), 2);
+        assert!(player.next_track().is_err());
+        assert_eq!(player.get_track(), 2);
+
+        // Playlist sets the order and first track, invalid tracks are skipped
+        let mut nsf = Nsf::load(&mut Cursor::new(create_nsf())).unwrap();
+        nsf.playlist = vec![2, 7, 0, 2];
+        let mut player = NsfPlayer::new(nsf).unwrap();
+        assert_eq!(player.get_track(), 2);
+        assert!(player.previous_track().is_err());
+        player.next_track().unwrap();
+        assert_eq!(player.get_track(), 0);
+        player.next_track().unwrap();
+        assert_eq!(player.get_track(), 2);
+        assert!(player.next_track().is_err());
+        player.previous_track().unwrap();
+        assert_eq!(player.get_track(), 0);
+    }
+}
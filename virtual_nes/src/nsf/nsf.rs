@@ -0,0 +1,261 @@
+use super::super::region::Region;
+use byteorder::{ByteOrder, LittleEndian};
+use failure::Error;
+use std::io::Read;
+
+const NSF_MAGIC: &[u8] = b"NESM\x1A";
+const NSFE_MAGIC: &[u8] = b"NSFE";
+const NSF_HEADER_SIZE: usize = 0x80;
+
+// Play rates in microseconds when not specified
+const NTSC_SPEED: u16 = 16639;
+const PAL_SPEED: u16 = 19997;
+
+/// NES Sound Format rip: 6502 code and data with init and play routines.
+///
+/// https://wiki.nesdev.com/w/index.php/NSF
+/// https://wiki.nesdev.com/w/index.php/NSFe
+#[derive(Debug, Clone)]
+pub struct Nsf {
+    pub title: String,
+    pub artist: String,
+    pub copyright: String,
+    pub ripper: String,
+    pub songs: u8,
+    pub start_song: u8, // 0-based
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+    pub ntsc_speed: u16, // microseconds between play calls
+    pub pal_speed: u16,
+    pub region: Region,
+    pub banks: Option<[u8; 8]>, // initial banks if bankswitched
+    pub expansion: u8,          // extra sound chips, not emulated
+    pub data: Vec<u8>,
+    // NSFe extras, empty when not present
+    pub playlist: Vec<u8>,
+    pub track_labels: Vec<String>,
+    pub track_times: Vec<Option<u32>>, // milliseconds
+}
+
+impl Nsf {
+    /// Load from NSF or NSFe file.
+    pub fn load<T: Read>(reader: &mut T) -> Result<Self, Error> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        if data.starts_with(NSF_MAGIC) {
+            Nsf::parse_nsf(&data)
+        } else if data.starts_with(NSFE_MAGIC) {
+            Nsf::parse_nsfe(&data[NSFE_MAGIC.len()..])
+        } else {
+            Err(format_err!("This is not NSF or NSFe file: magic mismatch"))
+        }
+    }
+
+    fn empty() -> Nsf {
+        Nsf {
+            title: String::new(),
+            artist: String::new(),
+            copyright: String::new(),
+            ripper: String::new(),
+            songs: 1,
+            start_song: 0,
+            load_address: 0x8000,
+            init_address: 0x8000,
+            play_address: 0x8000,
+            ntsc_speed: NTSC_SPEED,
+            pal_speed: PAL_SPEED,
+            region: Region::Ntsc,
+            banks: None,
+            expansion: 0,
+            data: Vec::new(),
+            playlist: Vec::new(),
+            track_labels: Vec::new(),
+            track_times: Vec::new(),
+        }
+    }
+
+    fn parse_nsf(data: &[u8]) -> Result<Nsf, Error> {
+        if data.len() <= NSF_HEADER_SIZE {
+            return Err(format_err!("NSF file is too short: {} bytes", data.len()));
+        }
+
+        let header = &data[..NSF_HEADER_SIZE];
+        let banks = [
+            header[0x70],
+            header[0x71],
+            header[0x72],
+            header[0x73],
+            header[0x74],
+            header[0x75],
+            header[0x76],
+            header[0x77],
+        ];
+        Ok(Nsf {
+            title: read_string(&header[0x0E..0x2E]),
+            artist: read_string(&header[0x2E..0x4E]),
+            copyright: read_string(&header[0x4E..0x6E]),
+            songs: header[0x06],
+            start_song: header[0x07].saturating_sub(1),
+            load_address: LittleEndian::read_u16(&header[0x08..]),
+            init_address: LittleEndian::read_u16(&header[0x0A..]),
+            play_address: LittleEndian::read_u16(&header[0x0C..]),
+            ntsc_speed: LittleEndian::read_u16(&header[0x6E..]),
+            pal_speed: LittleEndian::read_u16(&header[0x78..]),
+            region: read_region(header[0x7A]),
+            banks: if banks.iter().any(|b| *b != 0) {
+                Some(banks)
+            } else {
+                None
+            },
+            expansion: header[0x7B],
+            data: data[NSF_HEADER_SIZE..].to_vec(),
+            ..Nsf::empty()
+        })
+    }
+
+    // NSFe is a list of chunks: length, four character id and data.
+    // Chunks starting with uppercase letter are required to understand.
+    fn parse_nsfe(mut data: &[u8]) -> Result<Nsf, Error> {
+        let mut nsf = Nsf::empty();
+        let mut has_info = false;
+        let mut has_data = false;
+
+        while data.len() >= 8 {
+            let length = LittleEndian::read_u32(data) as usize;
+            let id = &data[4..8];
+            if data.len() < 8 + length {
+                return Err(format_err!(
+                    "NSFe chunk {} is truncated",
+                    String::from_utf8_lossy(id)
+                ));
+            }
+            let chunk = &data[8..8 + length];
+            data = &data[8 + length..];
+
+            match id {
+                b"INFO" => {
+                    if chunk.len() < 8 {
+                        return Err(format_err!("NSFe INFO chunk is too short"));
+                    }
+                    nsf.load_address = LittleEndian::read_u16(&chunk[0..]);
+                    nsf.init_address = LittleEndian::read_u16(&chunk[2..]);
+                    nsf.play_address = LittleEndian::read_u16(&chunk[4..]);
+                    nsf.region = read_region(chunk[6]);
+                    nsf.expansion = chunk[7];
+                    nsf.songs = chunk.get(8).cloned().unwrap_or(1);
+                    nsf.start_song = chunk.get(9).cloned().unwrap_or(0);
+                    has_info = true;
+                }
+                b"DATA" => {
+                    nsf.data = chunk.to_vec();
+                    has_data = true;
+                }
+                b"BANK" => {
+                    let mut banks = [0; 8];
+                    for (bank, value) in banks.iter_mut().zip(chunk) {
+                        *bank = *value;
+                    }
+                    nsf.banks = Some(banks);
+                }
+                b"RATE" => {
+                    if chunk.len() >= 2 {
+                        nsf.ntsc_speed = LittleEndian::read_u16(&chunk[0..]);
+                    }
+                    if chunk.len() >= 4 {
+                        nsf.pal_speed = LittleEndian::read_u16(&chunk[2..]);
+                    }
+                }
+                b"NEND" => break,
+                b"auth" => {
+                    let mut strings = read_strings(chunk).into_iter();
+                    nsf.title = strings.next().unwrap_or_default();
+                    nsf.artist = strings.next().unwrap_or_default();
+                    nsf.copyright = strings.next().unwrap_or_default();
+                    nsf.ripper = strings.next().unwrap_or_default();
+                }
+                b"plst" => nsf.playlist = chunk.to_vec(),
+                b"tlbl" => nsf.track_labels = read_strings(chunk),
+                b"time" => {
+                    nsf.track_times = chunk
+                        .chunks(4)
+                        .filter(|time| time.len() == 4)
+                        .map(|time| match LittleEndian::read_i32(time) {
+                            time if time < 0 => None,
+                            time => Some(time as u32),
+                        })
+                        .collect();
+                }
+                id if id[0].is_ascii_uppercase() => {
+                    return Err(format_err!(
+                        "NSFe chunk {} not supported yet.",
+                        String::from_utf8_lossy(id)
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        if !has_info || !has_data {
+            return Err(format_err!("NSFe file has no INFO or DATA chunk"));
+        }
+        Ok(nsf)
+    }
+
+    // 0-based tracks in the order they are meant to be played,
+    // NSFe playlist if it has any valid track
+    pub fn play_order(&self) -> Vec<u8> {
+        let playlist: Vec<u8> = self
+            .playlist
+            .iter()
+            .cloned()
+            .filter(|track| *track < self.songs)
+            .collect();
+        if playlist.is_empty() {
+            (0..self.songs).collect()
+        } else {
+            playlist
+        }
+    }
+
+    // Microseconds between play calls in given region
+    pub fn play_speed(&self, region: Region) -> u16 {
+        let speed = match region {
+            Region::Ntsc => self.ntsc_speed,
+            _ => self.pal_speed,
+        };
+        match (speed, region) {
+            (0, Region::Ntsc) => NTSC_SPEED,
+            (0, _) => PAL_SPEED,
+            (speed, _) => speed,
+        }
+    }
+}
+
+// Bit 0 selects PAL, bit 1 marks tunes working on both
+fn read_region(flags: u8) -> Region {
+    if flags & 0x03 == 0x01 {
+        Region::Pal
+    } else {
+        Region::Ntsc
+    }
+}
+
+// Zero padded string
+fn read_string(data: &[u8]) -> String {
+    let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+// Sequence of zero terminated strings
+fn read_strings(data: &[u8]) -> Vec<String> {
+    let data = match data.last() {
+        Some(0) => &data[..data.len() - 1],
+        _ => data,
+    };
+    if data.is_empty() {
+        return Vec::new();
+    }
+    data.split(|b| *b == 0).map(read_string).collect()
+}
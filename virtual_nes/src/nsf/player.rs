@@ -0,0 +1,228 @@
+use super::super::apu::APU;
+use super::super::cpu::{Bus, StopReason, CPU};
+use super::super::mapper::Mapper;
+use super::super::region::Region;
+use super::bus::NsfBus;
+use super::mapper::NsfMapper;
+use super::nsf::Nsf;
+use failure::Error;
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+// Return address of INIT and PLAY calls. CPU is idle while PC is here,
+// the address itself is never executed.
+const IDLE_ADDRESS: u16 = 0x5FF0;
+
+// CPU cycles run between checks for finished samples
+const RENDER_CHUNK: usize = 1024;
+
+/// Plays NSF tunes on CPU and APU without PPU.
+#[derive(Debug)]
+pub struct NsfPlayer {
+    cpu: CPU<NsfBus>,
+    nsf: Nsf,
+    region: Region,
+    track: u8,
+    position: usize, // of the track in play order
+    play_clock: u64, // accumulates microseconds times CPU frequency
+    samples: Vec<f32>,
+}
+
+impl NsfPlayer {
+    pub fn new(nsf: Nsf) -> Result<NsfPlayer, Error> {
+        let mapper: Box<dyn Mapper> = Box::new(NsfMapper::from_nsf(&nsf)?);
+        let mapper = Rc::new(RefCell::new(mapper));
+        let apu = APU::new(Rc::clone(&mapper));
+        let bus = NsfBus::new(mapper, apu);
+
+        let region = nsf.region;
+        let mut player = NsfPlayer {
+            cpu: CPU::new(bus),
+            nsf: nsf,
+            region: region,
+            track: 0,
+            position: 0,
+            play_clock: 0,
+            samples: Vec::new(),
+        };
+        player.set_region(region);
+        // Playlist may leave out the start song
+        let order = player.nsf.play_order();
+        let track = if order.contains(&player.nsf.start_song) {
+            player.nsf.start_song
+        } else {
+            order[0]
+        };
+        player.start_track(track)?;
+        Ok(player)
+    }
+
+    pub fn nsf(&self) -> &Nsf {
+        &self.nsf
+    }
+
+    // Overrides the region requested by the tune, the tune is told
+    // about it on the next start_track
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.cpu.mem.set_region(region);
+    }
+
+    pub fn get_region(&self) -> Region {
+        self.region
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.cpu.mem.apu.set_sample_rate(sample_rate);
+    }
+
+    pub fn get_sample_rate(&self) -> u32 {
+        self.cpu.mem.apu.get_sample_rate()
+    }
+
+    // CPU view of memory, for inspecting state of the tune
+    pub fn read_memory(&self, address: u16) -> u8 {
+        self.cpu.mem.peek(address)
+    }
+
+    // Records APU register writes until stop_vgm_log
+    pub fn start_vgm_log(&mut self) {
+        self.cpu.mem.apu.start_vgm_log();
+    }
+
+    // Writes VGM file with APU writes since start_vgm_log
+    pub fn stop_vgm_log(&mut self, writer: &mut dyn Write) -> Result<(), Error> {
+        match self.cpu.mem.apu.stop_vgm_log() {
+            Some(vgm) => Ok(writer.write_all(&vgm)?),
+            None => Err(format_err!("VGM logging was not started")),
+        }
+    }
+
+    pub fn get_track(&self) -> u8 {
+        self.track
+    }
+
+    // Resets memory and APU and calls INIT for 0-based track number
+    pub fn start_track(&mut self, track: u8) -> Result<(), Error> {
+        if track >= self.nsf.songs {
+            return Err(format_err!(
+                "Track {} is out of range, NSF has {} tracks",
+                track,
+                self.nsf.songs
+            ));
+        }
+        self.track = track;
+        if let Some(position) = self.nsf.play_order().iter().position(|t| *t == track) {
+            self.position = position;
+        }
+
+        for i in 0..0x0800 {
+            self.cpu.mem.write(i, 0);
+        }
+        for i in 0x6000..0x8000 {
+            self.cpu.mem.write(i, 0);
+        }
+        let banks = self.nsf.banks.unwrap_or([0, 1, 2, 3, 4, 5, 6, 7]);
+        for (i, bank) in banks.iter().enumerate() {
+            self.cpu.mem.write(0x5FF8 + i as u16, *bank);
+        }
+
+        for address in 0x4000..0x4014 {
+            self.cpu.mem.write(address, 0);
+        }
+        self.cpu.mem.write(0x4015, 0x00);
+        self.cpu.mem.write(0x4015, 0x0F);
+        self.cpu.mem.write(0x4017, 0x40);
+
+        // Also brings back CPU jammed by previous track
+        self.cpu.reset();
+        self.cpu.sp = 0xFD;
+        self.cpu.set_flags(0x24);
+        self.cpu.a = track;
+        self.cpu.x = if self.region == Region::Pal { 1 } else { 0 };
+        self.cpu.y = 0;
+        let init = self.nsf.init_address;
+        self.call(init);
+
+        self.play_clock = 0;
+        self.samples.clear();
+        Ok(())
+    }
+
+    // Starts the track following the current one in play order
+    pub fn next_track(&mut self) -> Result<(), Error> {
+        let position = self.position + 1;
+        self.start_position(position)
+    }
+
+    pub fn previous_track(&mut self) -> Result<(), Error> {
+        match self.position {
+            0 => Err(format_err!("Track {} is the first one", self.track)),
+            position => self.start_position(position - 1),
+        }
+    }
+
+    // Playlist may repeat a track, so position is kept explicitly
+    fn start_position(&mut self, position: usize) -> Result<(), Error> {
+        match self.nsf.play_order().get(position) {
+            Some(&track) => {
+                self.start_track(track)?;
+                self.position = position;
+                Ok(())
+            }
+            None => Err(format_err!("Track {} is the last one", self.track)),
+        }
+    }
+
+    // Jumps to subroutine that returns to idle address
+    fn call(&mut self, address: u16) {
+        self.cpu.push_word(IDLE_ADDRESS - 1);
+        self.cpu.pc = address;
+    }
+
+    fn idle(&self) -> bool {
+        self.cpu.pc == IDLE_ADDRESS
+    }
+
+    // Runs an instruction, or a single cycle while idle.
+    // PLAY is called at the tune rate once previous call has returned.
+    pub fn step(&mut self) -> Result<usize, StopReason> {
+        let cycles = if self.idle() {
+            // DMC fetches don't stall idle CPU
+            self.cpu.mem.tick();
+            self.cpu.mem.take_stall();
+            1
+        } else {
+            self.cpu.step()?
+        };
+
+        let frequency = self.region.cpu_frequency() as u64;
+        let period = self.nsf.play_speed(self.region) as u64 * frequency;
+        self.play_clock += cycles as u64 * 1_000_000;
+        if self.play_clock >= period && self.idle() {
+            // Calls missed while PLAY overran are dropped
+            self.play_clock %= period;
+            let play = self.nsf.play_address;
+            self.call(play);
+        }
+        Ok(cycles)
+    }
+
+    /// Renders given number of mono samples at the sample rate.
+    pub fn render(&mut self, count: usize) -> Result<Vec<f32>, StopReason> {
+        while self.samples.len() < count {
+            let mut cycles = 0;
+            while cycles < RENDER_CHUNK {
+                cycles += self.step()?;
+            }
+            self.cpu.mem.apu.read_samples(&mut self.samples);
+        }
+        Ok(self.samples.drain(..count).collect())
+    }
+
+    pub fn render_seconds(&mut self, seconds: f32) -> Result<Vec<f32>, StopReason> {
+        let count = (self.get_sample_rate() as f32 * seconds) as usize;
+        self.render(count)
+    }
+}
@@ -0,0 +1,105 @@
+use super::super::mapper::Mapper;
+use super::super::memory::Memory;
+use super::super::rom::Mirroring;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Debug)]
+pub struct PpuMemory {
+    pub mapper: Rc<RefCell<Box<dyn Mapper>>>,
+    pub ciram: Vec<u8>, // console internal nametable RAM
+    pub palette: Vec<u8>,
+}
+
+impl PpuMemory {
+    pub fn new(mapper: Rc<RefCell<Box<dyn Mapper>>>) -> PpuMemory {
+        PpuMemory {
+            mapper: mapper,
+            ciram: vec![0; 2048],
+            palette: vec![0; 32],
+        }
+    }
+
+    // Puts address on the external PPU bus
+    pub fn notify_address(&self, address: u16) {
+        self.mapper.borrow_mut().ppu_address(address);
+    }
+
+    // Mirroring is queried on every access, mappers may switch it any time
+    fn read_nametable(&self, address: u16) -> u8 {
+        if let Some(value) = self.mapper.borrow_mut().read_nametable(address) {
+            return value;
+        }
+        let mirroring = *self.mapper.borrow().get_mirroring();
+        match mirror_address(mirroring, address) {
+            offset if offset < 0x0800 => self.ciram[offset],
+            offset => self.mapper.borrow().read_vram(offset - 0x0800),
+        }
+    }
+
+    fn write_nametable(&mut self, address: u16, value: u8) {
+        if self.mapper.borrow_mut().write_nametable(address, value) {
+            return;
+        }
+        let mirroring = *self.mapper.borrow().get_mirroring();
+        match mirror_address(mirroring, address) {
+            offset if offset < 0x0800 => self.ciram[offset] = value,
+            offset => self.mapper.borrow_mut().write_vram(offset - 0x0800, value),
+        }
+    }
+}
+
+// Maps $2000-$3EFF to an offset in four 1 KiB nametables, the first two of
+// which are CIRAM and the rest are provided by four-screen cartridges
+fn mirror_address(mirroring: Mirroring, address: u16) -> usize {
+    let address = (address as usize - 0x2000) % 0x1000;
+    let table = address / 0x0400;
+    let offset = address % 0x0400;
+    let physical = match mirroring {
+        Mirroring::Horizontal => [0, 0, 1, 1][table],
+        Mirroring::Vertical => [0, 1, 0, 1][table],
+        Mirroring::Single0 => 0,
+        Mirroring::Single1 => 1,
+        Mirroring::FourScreen => table,
+    };
+    physical * 0x0400 + offset
+}
+
+// Palette RAM is mirrored every 32 bytes, and sprite palette entries
+// $3F10/$3F14/$3F18/$3F1C are mirrors of $3F00/$3F04/$3F08/$3F0C
+fn palette_address(address: u16) -> usize {
+    let index = address as usize % 32;
+    if index >= 16 && index.is_multiple_of(4) {
+        index - 16
+    } else {
+        index
+    }
+}
+
+impl Memory for PpuMemory {
+    fn read(&self, address: u16) -> u8 {
+        let address = address % 0x4000;
+        if address < 0x3F00 {
+            self.notify_address(address);
+        }
+        match address {
+            0x0000..0x2000 => self.mapper.borrow().read(address),
+            0x2000..0x3F00 => self.read_nametable(address),
+            0x3F00..0x4000 => self.palette[palette_address(address)],
+            _ => unreachable!(),
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        let address = address % 0x4000;
+        if address < 0x3F00 {
+            self.notify_address(address);
+        }
+        match address {
+            0x0000..0x2000 => self.mapper.borrow_mut().write(address, value),
+            0x2000..0x3F00 => self.write_nametable(address, value),
+            0x3F00..0x4000 => self.palette[palette_address(address)] = value & 0x3F,
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,602 @@
+mod memory;
+mod palette;
+mod ppu;
+
+pub use self::palette::Palette;
+pub use self::ppu::{PPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapper::{create_mapper, Mapper};
+    use super::super::memory::Memory;
+    use super::super::region::Region;
+    use super::super::rom::Mirroring;
+    use super::super::tests::{create_rom, run_test_rom};
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    fn create_ppu() -> PPU {
+        let rom = create_rom("nestest/nestest").unwrap();
+        let mapper = Rc::new(RefCell::new(create_mapper(rom).unwrap()));
+        let mut ppu = PPU::new(mapper);
+        ppu.reset();
+        ppu
+    }
+
+    fn create_ppu_with_mirroring(mirroring: Mirroring) -> PPU {
+        let mut rom = create_rom("nestest/nestest").unwrap();
+        rom.mirroring = mirroring;
+        if mirroring == Mirroring::FourScreen {
+            rom.vram = vec![0; 2048];
+        }
+        let mapper = Rc::new(RefCell::new(create_mapper(rom).unwrap()));
+        PPU::new(mapper)
+    }
+
+    fn set_address(ppu: &mut PPU, address: u16) {
+        ppu.write_register(0x2006, (address >> 8) as u8);
+        ppu.write_register(0x2006, address as u8);
+    }
+
+    #[test]
+    fn data_read_buffer() {
+        let mut ppu = create_ppu();
+
+        set_address(&mut ppu, 0x2108);
+        ppu.write_register(0x2007, 0x11);
+        ppu.write_register(0x2007, 0x22);
+
+        // First read after setting the address returns the stale buffer
+        set_address(&mut ppu, 0x2108);
+        ppu.read_register(0x2007);
+        assert_eq!(ppu.read_register(0x2007), 0x11);
+        assert_eq!(ppu.read_register(0x2007), 0x22);
+    }
+
+    #[test]
+    fn data_increment_32() {
+        let mut ppu = create_ppu();
+
+        ppu.write_register(0x2000, 0x04);
+        set_address(&mut ppu, 0x2000);
+        ppu.write_register(0x2007, 0x33);
+        ppu.write_register(0x2007, 0x44);
+
+        ppu.write_register(0x2000, 0x00);
+        set_address(&mut ppu, 0x2020);
+        ppu.read_register(0x2007);
+        assert_eq!(ppu.read_register(0x2007), 0x44);
+    }
+
+    #[test]
+    fn palette_read() {
+        let mut ppu = create_ppu();
+
+        set_address(&mut ppu, 0x2F05);
+        ppu.write_register(0x2007, 0x55);
+        set_address(&mut ppu, 0x3F05);
+        ppu.write_register(0x2007, 0x0A);
+
+        // Palette reads are not buffered, but the buffer is filled from $2Fxx
+        set_address(&mut ppu, 0x3F05);
+        assert_eq!(ppu.read_register(0x2007), 0x0A);
+        set_address(&mut ppu, 0x2000);
+        assert_eq!(ppu.read_register(0x2007), 0x55);
+    }
+
+    #[test]
+    fn status_resets_toggle() {
+        let mut ppu = create_ppu();
+
+        set_address(&mut ppu, 0x2400);
+        ppu.write_register(0x2007, 0x66);
+
+        // Dangling first write is discarded by reading PPUSTATUS
+        ppu.write_register(0x2006, 0x3F);
+        ppu.read_register(0x2002);
+        set_address(&mut ppu, 0x2400);
+        ppu.read_register(0x2007);
+        assert_eq!(ppu.read_register(0x2007), 0x66);
+    }
+
+    #[test]
+    fn oam_data() {
+        let mut ppu = create_ppu();
+
+        ppu.write_register(0x2003, 0x10);
+        ppu.write_register(0x2004, 0x77);
+        ppu.write_register(0x2004, 0xFF);
+
+        ppu.write_register(0x2003, 0x10);
+        assert_eq!(ppu.read_register(0x2004), 0x77);
+        // Reads do not increment OAMADDR
+        assert_eq!(ppu.read_register(0x2004), 0x77);
+        // Attribute byte has unimplemented bits
+        ppu.write_register(0x2003, 0x11);
+        assert_eq!(ppu.read_register(0x2004), 0xFF);
+        ppu.write_register(0x2003, 0x12);
+        ppu.write_register(0x2004, 0xFF);
+        ppu.write_register(0x2003, 0x12);
+        assert_eq!(ppu.read_register(0x2004), 0xE3);
+    }
+
+    // Runs until the end of the next completed frame, returns dots taken
+    fn step_frame(ppu: &mut PPU) -> usize {
+        let mut dots = 0;
+        loop {
+            ppu.step();
+            dots += 1;
+            if ppu.take_frame_complete() {
+                return dots;
+            }
+        }
+    }
+
+    #[test]
+    fn frame_timing() {
+        let mut ppu = create_ppu();
+
+        step_frame(&mut ppu);
+        assert_eq!(ppu.read_register(0x2002) & 0x80, 0x80);
+        assert_eq!(step_frame(&mut ppu), 341 * 262);
+
+        // Odd frames are one dot shorter while rendering
+        ppu.write_register(0x2001, 0x08);
+        let dots = step_frame(&mut ppu) + step_frame(&mut ppu);
+        assert_eq!(dots, 341 * 262 * 2 - 1);
+    }
+
+    #[test]
+    fn background_tile() {
+        let mut ppu = create_ppu();
+
+        write_half_tile(&mut ppu);
+        set_address(&mut ppu, 0x2000);
+        ppu.write_register(0x2007, 0x01);
+        set_address(&mut ppu, 0x3F00);
+        ppu.write_register(0x2007, 0x0F);
+        ppu.write_register(0x2007, 0x01);
+        ppu.write_register(0x2007, 0x02);
+        ppu.write_register(0x2007, 0x03);
+
+        // PPUADDR shares the scroll register, reset the nametable too
+        ppu.write_register(0x2000, 0);
+        ppu.write_register(0x2005, 0);
+        ppu.write_register(0x2005, 0);
+        ppu.write_register(0x2001, 0x0A);
+        step_frame(&mut ppu);
+        step_frame(&mut ppu);
+
+        let frame = ppu.frame();
+        assert_eq!(frame[0], 0x03);
+        assert_eq!(frame[3], 0x03);
+        assert_eq!(frame[4], 0x0F);
+        assert_eq!(frame[7 * SCREEN_WIDTH + 3], 0x03);
+        assert_eq!(frame[8 * SCREEN_WIDTH], 0x0F);
+
+        // Fine horizontal scroll moves the tile to the left
+        ppu.write_register(0x2005, 2);
+        ppu.write_register(0x2005, 0);
+        step_frame(&mut ppu);
+        let frame = ppu.frame();
+        assert_eq!(frame[1], 0x03);
+        assert_eq!(frame[2], 0x0F);
+    }
+
+    // Tile $01 is a solid block of color 3 in the left half
+    fn write_half_tile(ppu: &mut PPU) {
+        for row in 0..8 {
+            ppu.mem.write(0x0010 + row, 0xF0);
+            ppu.mem.write(0x0018 + row, 0xF0);
+        }
+    }
+
+    fn write_sprite(ppu: &mut PPU, index: usize, y: u8, tile: u8, attribute: u8, x: u8) {
+        ppu.oam[index * 4..index * 4 + 4].copy_from_slice(&[y, tile, attribute, x]);
+    }
+
+    #[test]
+    fn sprite_zero_hit() {
+        let mut ppu = create_ppu();
+        write_half_tile(&mut ppu);
+        set_address(&mut ppu, 0x2000);
+        ppu.write_register(0x2007, 0x01);
+        for i in 0..64 {
+            write_sprite(&mut ppu, i, 0xFF, 0, 0, 0);
+        }
+
+        // Sprite 0 overlaps the opaque half of the background tile
+        write_sprite(&mut ppu, 0, 0, 0x01, 0, 2);
+        ppu.write_register(0x2001, 0x1E);
+        step_frame(&mut ppu);
+        step_frame(&mut ppu);
+        assert_eq!(ppu.read_register(0x2002) & 0x40, 0x40);
+
+        // Horizontally flipped it lands on the transparent half
+        write_sprite(&mut ppu, 0, 0, 0x01, 0x40, 4);
+        step_frame(&mut ppu);
+        assert_eq!(ppu.read_register(0x2002) & 0x40, 0x00);
+    }
+
+    #[test]
+    fn sprite_overflow() {
+        let mut ppu = create_ppu();
+        for i in 0..64 {
+            write_sprite(&mut ppu, i, 0xFF, 0, 0, 0);
+        }
+        for i in 0..8 {
+            write_sprite(&mut ppu, i, 0x20, 0, 0, i as u8 * 8);
+        }
+        ppu.write_register(0x2001, 0x18);
+        step_frame(&mut ppu);
+        step_frame(&mut ppu);
+        assert_eq!(ppu.read_register(0x2002) & 0x20, 0x00);
+
+        write_sprite(&mut ppu, 8, 0x20, 0, 0, 0);
+        step_frame(&mut ppu);
+        assert_eq!(ppu.read_register(0x2002) & 0x20, 0x20);
+
+        // Search after the eighth sprite reads tile of the next one as Y,
+        // false positive with only eight sprites on the line
+        write_sprite(&mut ppu, 8, 0xFF, 0, 0, 0);
+        write_sprite(&mut ppu, 9, 0xFF, 0x20, 0, 0);
+        step_frame(&mut ppu);
+        assert_eq!(ppu.read_register(0x2002) & 0x20, 0x20);
+
+        // and misses the ninth sprite for the same reason
+        write_sprite(&mut ppu, 9, 0x20, 0, 0, 0);
+        step_frame(&mut ppu);
+        assert_eq!(ppu.read_register(0x2002) & 0x20, 0x00);
+    }
+
+    #[test]
+    fn sprite_rendering() {
+        let mut ppu = create_ppu();
+        write_half_tile(&mut ppu);
+        for i in 0..64 {
+            write_sprite(&mut ppu, i, 0xFF, 0, 0, 0);
+        }
+        set_address(&mut ppu, 0x3F00);
+        ppu.write_register(0x2007, 0x0F);
+        set_address(&mut ppu, 0x3F17);
+        ppu.write_register(0x2007, 0x16);
+
+        // Palette 1, flipped horizontally: opaque in columns 4-7
+        write_sprite(&mut ppu, 0, 9, 0x01, 0x41, 16);
+        ppu.write_register(0x2001, 0x1E);
+        step_frame(&mut ppu);
+        step_frame(&mut ppu);
+
+        let frame = ppu.frame();
+        assert_eq!(frame[10 * SCREEN_WIDTH + 19], 0x0F);
+        assert_eq!(frame[10 * SCREEN_WIDTH + 20], 0x16);
+        assert_eq!(frame[17 * SCREEN_WIDTH + 23], 0x16);
+        assert_eq!(frame[18 * SCREEN_WIDTH + 23], 0x0F);
+
+        // 8x16 sprites cover twice as many lines, tile $01 is the bottom half
+        write_sprite(&mut ppu, 0, 9, 0x00, 0x41, 16);
+        ppu.write_register(0x2000, 0x20);
+        step_frame(&mut ppu);
+        let frame = ppu.frame();
+        assert_eq!(frame[25 * SCREEN_WIDTH + 20], 0x16);
+    }
+
+    // Writes a marker into each nametable and returns what every
+    // nametable reads back afterwards
+    fn nametable_layout(ppu: &mut PPU) -> [u8; 4] {
+        for i in 0..4 {
+            ppu.mem.write(0x2000 + i * 0x0400, i as u8 + 1);
+        }
+        let mut layout = [0; 4];
+        for i in 0..4 {
+            layout[i] = ppu.mem.read(0x2000 + i as u16 * 0x0400);
+        }
+        layout
+    }
+
+    #[test]
+    fn nametable_mirroring() {
+        let mut ppu = create_ppu_with_mirroring(Mirroring::Horizontal);
+        assert_eq!(nametable_layout(&mut ppu), [2, 2, 4, 4]);
+
+        let mut ppu = create_ppu_with_mirroring(Mirroring::Vertical);
+        assert_eq!(nametable_layout(&mut ppu), [3, 4, 3, 4]);
+
+        let mut ppu = create_ppu_with_mirroring(Mirroring::Single0);
+        assert_eq!(nametable_layout(&mut ppu), [4, 4, 4, 4]);
+
+        let mut ppu = create_ppu_with_mirroring(Mirroring::FourScreen);
+        assert_eq!(nametable_layout(&mut ppu), [1, 2, 3, 4]);
+
+        // $3000-$3EFF mirrors $2000-$2EFF
+        assert_eq!(ppu.mem.read(0x3400), 2);
+    }
+
+    #[test]
+    fn mapper_mirroring_switch() {
+        let rom = create_rom("instr_test-v5/official_only").unwrap();
+        let mapper = Rc::new(RefCell::new(create_mapper(rom).unwrap()));
+        let mut ppu = PPU::new(Rc::clone(&mapper));
+
+        // MMC1 control register is loaded serially, one bit per write,
+        // writes on consecutive CPU cycles are ignored
+        let write_control = |value: u8| {
+            for i in 0..5 {
+                mapper.borrow_mut().step();
+                mapper.borrow_mut().write(0x8000, (value >> i) & 1);
+                mapper.borrow_mut().step();
+            }
+        };
+
+        write_control(0x02);
+        assert_eq!(nametable_layout(&mut ppu), [3, 4, 3, 4]);
+        write_control(0x03);
+        assert_eq!(nametable_layout(&mut ppu), [2, 2, 4, 4]);
+        write_control(0x01);
+        ppu.mem.write(0x2000, 0x55);
+        assert_eq!(ppu.mem.read(0x2C00), 0x55);
+    }
+
+    // Steps until the next dot to be executed is at given position,
+    // returns whether NMI was triggered on the way
+    fn step_to(ppu: &mut PPU, scanline: u16, cycle: u16) -> bool {
+        let mut nmi = false;
+        while ppu.get_scanline() != scanline || ppu.get_cycle() != cycle {
+            nmi |= ppu.step();
+        }
+        nmi
+    }
+
+    fn step_dots(ppu: &mut PPU, dots: usize) -> bool {
+        let mut nmi = false;
+        for _ in 0..dots {
+            nmi |= ppu.step();
+        }
+        nmi
+    }
+
+    #[test]
+    fn vblank_nmi() {
+        let mut ppu = create_ppu();
+        ppu.write_register(0x2000, 0x80);
+
+        step_to(&mut ppu, 241, 0);
+        assert_eq!(ppu.read_register(0x2002) & 0x80, 0x00);
+        assert!(!step_dots(&mut ppu, 3));
+        assert!(step_dots(&mut ppu, 1));
+        assert_eq!(ppu.read_register(0x2002) & 0x80, 0x80);
+
+        // Cleared at the start of pre-render line
+        step_to(&mut ppu, 261, 1);
+        ppu.step();
+        assert_eq!(ppu.read_register(0x2002) & 0x80, 0x00);
+    }
+
+    #[test]
+    fn vblank_nmi_suppression() {
+        let mut ppu = create_ppu();
+        ppu.write_register(0x2000, 0x80);
+
+        // Reading one dot before vblank: flag is never set, no NMI
+        step_to(&mut ppu, 241, 1);
+        ppu.read_register(0x2002);
+        assert!(!step_dots(&mut ppu, 10));
+        assert_eq!(ppu.read_register(0x2002) & 0x80, 0x00);
+
+        // Reading right after the flag is set: flag reads set, no NMI
+        step_to(&mut ppu, 241, 2);
+        assert_eq!(ppu.read_register(0x2002) & 0x80, 0x80);
+        assert!(!step_dots(&mut ppu, 10));
+
+        // Disabling NMI right after vblank also suppresses it
+        step_to(&mut ppu, 241, 2);
+        ppu.write_register(0x2000, 0x00);
+        assert!(!step_dots(&mut ppu, 10));
+
+        // Reading a bit later does not
+        ppu.write_register(0x2000, 0x80);
+        step_to(&mut ppu, 241, 4);
+        assert_eq!(ppu.read_register(0x2002) & 0x80, 0x80);
+    }
+
+    #[test]
+    fn nmi_enable_during_vblank() {
+        let mut ppu = create_ppu();
+
+        step_to(&mut ppu, 250, 0);
+        ppu.write_register(0x2000, 0x80);
+        assert!(step_dots(&mut ppu, 3));
+
+        // Toggling PPUCTRL bit 7 re-triggers NMI
+        ppu.write_register(0x2000, 0x00);
+        ppu.write_register(0x2000, 0x80);
+        assert!(step_dots(&mut ppu, 3));
+
+        // But no NMI once vblank flag is acknowledged
+        ppu.read_register(0x2002);
+        ppu.write_register(0x2000, 0x00);
+        ppu.write_register(0x2000, 0x80);
+        assert!(!step_dots(&mut ppu, 3));
+    }
+
+    #[test]
+    fn palette_mirroring() {
+        let mut ppu = create_ppu();
+
+        set_address(&mut ppu, 0x3F10);
+        ppu.write_register(0x2007, 0x21);
+        ppu.write_register(0x2007, 0x22);
+        set_address(&mut ppu, 0x3F00);
+        assert_eq!(ppu.read_register(0x2007), 0x21);
+        assert_eq!(ppu.read_register(0x2007), 0x00);
+
+        // Palette RAM repeats every 32 bytes and is 6 bits wide
+        set_address(&mut ppu, 0x3FEC);
+        ppu.write_register(0x2007, 0xFF);
+        set_address(&mut ppu, 0x3F1C);
+        assert_eq!(ppu.read_register(0x2007), 0x3F);
+
+        // Greyscale masks out the hue
+        ppu.write_register(0x2001, 0x01);
+        set_address(&mut ppu, 0x3F1C);
+        assert_eq!(ppu.read_register(0x2007), 0x30);
+    }
+
+    #[test]
+    fn frame_emphasis() {
+        let mut ppu = create_ppu();
+
+        set_address(&mut ppu, 0x3F00);
+        ppu.write_register(0x2007, 0x16);
+        ppu.write_register(0x2001, 0xA1);
+        step_frame(&mut ppu);
+        assert_eq!(ppu.frame()[0], 0x10 | 0x140);
+
+        let palette = Palette::default();
+        let mut rgba = vec![0; 8];
+        palette.to_rgba(&[0x20, 0x20 | 0x40], &mut rgba);
+        assert_eq!(rgba[..4], [0xFF, 0xFE, 0xFF, 0xFF]);
+        // Red emphasis darkens green and blue
+        assert_eq!(rgba[4..], [0xFF, 0xCF, 0xD0, 0xFF]);
+    }
+
+    #[test]
+    fn palette_loading() {
+        let data: Vec<u8> = (0..192).map(|i| i as u8).collect();
+        let palette = Palette::load(&mut Cursor::new(data)).unwrap();
+        assert_eq!(palette.rgb(0x01), [3, 4, 5]);
+
+        let data: Vec<u8> = (0..1536).map(|i| (i / 3) as u8).collect();
+        let palette = Palette::load(&mut Cursor::new(data)).unwrap();
+        assert_eq!(palette.rgb(0x1FF), [0xFF, 0xFF, 0xFF]);
+
+        assert!(Palette::load(&mut Cursor::new(vec![0; 100])).is_err());
+    }
+
+    #[test]
+    fn scroll_split() {
+        let mut ppu = create_ppu();
+
+        write_half_tile(&mut ppu);
+        set_address(&mut ppu, 0x2000);
+        ppu.write_register(0x2007, 0x01);
+        set_address(&mut ppu, 0x3F00);
+        ppu.write_register(0x2007, 0x0F);
+        set_address(&mut ppu, 0x3F03);
+        ppu.write_register(0x2007, 0x03);
+
+        // Scrolled down by 16 pixels the tile is not visible
+        ppu.write_register(0x2000, 0);
+        ppu.write_register(0x2005, 0);
+        ppu.write_register(0x2005, 16);
+        ppu.write_register(0x2001, 0x0A);
+        step_frame(&mut ppu);
+        step_frame(&mut ppu);
+        assert_eq!(ppu.frame()[0], 0x0F);
+
+        // Mid-frame PPUADDR write points v back to the first row, and
+        // PPUSCROLL changes fine X for the following scanlines. Address bits
+        // 12-13 are fine Y, so $0000 is the top of the first nametable.
+        step_to(&mut ppu, 99, 300);
+        set_address(&mut ppu, 0x0000);
+        ppu.write_register(0x2005, 2);
+        ppu.read_register(0x2002);
+        step_frame(&mut ppu);
+
+        let frame = ppu.frame();
+        assert_eq!(frame[99 * SCREEN_WIDTH], 0x0F);
+        assert_eq!(frame[100 * SCREEN_WIDTH + 1], 0x03);
+        assert_eq!(frame[100 * SCREEN_WIDTH + 2], 0x0F);
+        assert_eq!(frame[107 * SCREEN_WIDTH + 1], 0x03);
+        assert_eq!(frame[108 * SCREEN_WIDTH + 1], 0x0F);
+    }
+
+    #[test]
+    fn region_timing() {
+        let mut ppu = create_ppu();
+        ppu.set_region(Region::Pal);
+        ppu.write_register(0x2001, 0x08);
+
+        // No odd frame dot skipping on PAL
+        step_frame(&mut ppu);
+        assert_eq!(ppu.get_scanline(), 241);
+        let dots = step_frame(&mut ppu) + step_frame(&mut ppu);
+        assert_eq!(dots, 341 * 312 * 2);
+
+        let mut ppu = create_ppu();
+        ppu.set_region(Region::Dendy);
+        step_frame(&mut ppu);
+        assert_eq!(ppu.get_scanline(), 291);
+        assert_eq!(step_frame(&mut ppu), 341 * 312);
+    }
+
+    // Board that counts A12 rising edges and fills nametables with $42
+    #[derive(Debug)]
+    struct ScanlineMapper {
+        mirroring: Mirroring,
+        a12: bool,
+        edges: Rc<Cell<usize>>,
+    }
+
+    impl Memory for ScanlineMapper {
+        fn read(&self, _address: u16) -> u8 {
+            0
+        }
+
+        fn write(&mut self, _address: u16, _value: u8) {}
+    }
+
+    impl Mapper for ScanlineMapper {
+        fn get_mirroring(&self) -> &Mirroring {
+            &self.mirroring
+        }
+
+        fn ppu_address(&mut self, address: u16) {
+            let a12 = address & 0x1000 != 0;
+            if a12 && !self.a12 {
+                self.edges.set(self.edges.get() + 1);
+            }
+            self.a12 = a12;
+        }
+
+        fn read_nametable(&mut self, _address: u16) -> Option<u8> {
+            Some(0x42)
+        }
+    }
+
+    #[test]
+    fn mapper_hooks() {
+        let edges = Rc::new(Cell::new(0));
+        let mapper: Box<dyn Mapper> = Box::new(ScanlineMapper {
+            mirroring: Mirroring::Horizontal,
+            a12: false,
+            edges: Rc::clone(&edges),
+        });
+        let mut ppu = PPU::new(Rc::new(RefCell::new(mapper)));
+
+        set_address(&mut ppu, 0x2000);
+        ppu.read_register(0x2007);
+        assert_eq!(ppu.read_register(0x2007), 0x42);
+
+        // Background from $0000 and sprites from $1000:
+        // one rising edge per rendered scanline
+        ppu.write_register(0x2000, 0x08);
+        ppu.write_register(0x2001, 0x18);
+        step_frame(&mut ppu);
+        edges.set(0);
+        step_frame(&mut ppu);
+        assert_eq!(edges.get(), 241);
+    }
+
+    #[test]
+    #[ignore = "needs ppu_vbl_nmi from nes-test-roms in ../roms"]
+    fn vbl_nmi_rom() {
+        // https://wiki.nesdev.com/w/index.php/Emulator_tests#PPU_Tests
+        // $2002 read race with VBL set, NMI suppression and re-triggering
+        let (code, status) = run_test_rom("ppu_vbl_nmi/ppu_vbl_nmi").unwrap();
+        assert_eq!(code, 0, "{}", status);
+        assert_eq!(status.trim_end(), "All 10 tests passed");
+    }
+}
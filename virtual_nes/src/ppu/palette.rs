@@ -0,0 +1,94 @@
+use failure::Error;
+use std::io::Read;
+
+const COLORS: usize = 64;
+const EMPHASIS_COLORS: usize = COLORS * 8;
+
+// Channels not selected by PPUMASK emphasis bits are darkened by this factor
+const ATTENUATION: f32 = 0.816;
+
+// 2C02 master palette
+const DEFAULT_PALETTE: [u32; COLORS] = [
+    0x666666, 0x002A88, 0x1412A7, 0x3B00A4, 0x5C007E, 0x6E0040, 0x6C0600, 0x561D00,
+    0x333500, 0x0B4800, 0x005200, 0x004F08, 0x00404D, 0x000000, 0x000000, 0x000000,
+    0xADADAD, 0x155FD9, 0x4240FF, 0x7527FE, 0xA01ACC, 0xB71E7B, 0xB53120, 0x994E00,
+    0x6B6D00, 0x388700, 0x0C9300, 0x008F32, 0x007C8D, 0x000000, 0x000000, 0x000000,
+    0xFFFEFF, 0x64B0FF, 0x9290FF, 0xC676FF, 0xF36AFF, 0xFE6ECC, 0xFE8170, 0xEA9E22,
+    0xBCBE00, 0x88D800, 0x5CE430, 0x45E082, 0x48CDDE, 0x4F4F4F, 0x000000, 0x000000,
+    0xFFFEFF, 0xC0DFFF, 0xD3D2FF, 0xE8C8FF, 0xFBC2FF, 0xFEC4EA, 0xFECCC5, 0xF7D8A5,
+    0xE4E594, 0xCFEF96, 0xBDF4AB, 0xB3F3CC, 0xB5EBF2, 0xB8B8B8, 0x000000, 0x000000,
+];
+
+/// Conversion table from PPU pixels to RGB colors.
+///
+/// Pixels are 6-bit palette indices with PPUMASK emphasis bits in bits 6-8,
+/// so the table holds 64 colors for each of the 8 emphasis combinations.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    colors: Vec<[u8; 3]>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        let colors = DEFAULT_PALETTE
+            .iter()
+            .map(|c| [(c >> 16) as u8, (c >> 8) as u8, *c as u8])
+            .collect();
+        Palette::with_emphasis(colors)
+    }
+}
+
+impl Palette {
+    /// Load from standard .pal file: 64 RGB triplets, optionally followed
+    /// by 7 more sets of 64 for every combination of emphasis bits.
+    pub fn load<T: Read>(reader: &mut T) -> Result<Self, Error> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        if data.len() != COLORS * 3 && data.len() != EMPHASIS_COLORS * 3 {
+            return Err(format_err!(
+                "Palette size must be 192 or 1536 bytes, got {}",
+                data.len()
+            ));
+        }
+
+        let colors: Vec<[u8; 3]> = data.chunks(3).map(|c| [c[0], c[1], c[2]]).collect();
+        if colors.len() == COLORS {
+            Ok(Palette::with_emphasis(colors))
+        } else {
+            Ok(Palette { colors: colors })
+        }
+    }
+
+    // Generates emphasized variants of the 64 base colors
+    fn with_emphasis(base: Vec<[u8; 3]>) -> Self {
+        let mut colors = Vec::with_capacity(EMPHASIS_COLORS);
+        for emphasis in 0..8 {
+            for (i, color) in base.iter().enumerate() {
+                let mut color = *color;
+                // Columns $xE and $xF are black regardless of emphasis
+                if emphasis != 0 && i & 0x0E != 0x0E {
+                    for (channel, value) in color.iter_mut().enumerate() {
+                        if emphasis & (1 << channel) == 0 {
+                            *value = (*value as f32 * ATTENUATION) as u8;
+                        }
+                    }
+                }
+                colors.push(color);
+            }
+        }
+        Palette { colors: colors }
+    }
+
+    pub fn rgb(&self, pixel: u16) -> [u8; 3] {
+        self.colors[pixel as usize % EMPHASIS_COLORS]
+    }
+
+    /// Converts PPU frame to RGBA, 4 bytes per pixel.
+    pub fn to_rgba(&self, frame: &[u16], buffer: &mut [u8]) {
+        for (pixel, rgba) in frame.iter().zip(buffer.chunks_mut(4)) {
+            let [r, g, b] = self.rgb(*pixel);
+            rgba.copy_from_slice(&[r, g, b, 0xFF]);
+        }
+    }
+}
@@ -0,0 +1,727 @@
+use super::super::mapper::Mapper;
+use super::super::memory::Memory;
+use super::super::region::Region;
+use super::memory::PpuMemory;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+
+const DOTS_PER_SCANLINE: u16 = 341;
+
+// Dots between the NMI line going high and the CPU seeing it. Reading
+// PPUSTATUS or clearing PPUCTRL bit 7 within this window suppresses NMI.
+const NMI_DELAY: u8 = 2;
+
+// PPUCTRL bits
+const CTRL_NAMETABLE: u8 = 0x03;
+const CTRL_INCREMENT: u8 = 0x04;
+const CTRL_SPRITE_TABLE: u8 = 0x08;
+const CTRL_BACKGROUND_TABLE: u8 = 0x10;
+const CTRL_SPRITE_SIZE: u8 = 0x20;
+const CTRL_NMI: u8 = 0x80;
+
+// PPUMASK bits
+const MASK_GREYSCALE: u8 = 0x01;
+const MASK_BACKGROUND_LEFT: u8 = 0x02;
+const MASK_SPRITES_LEFT: u8 = 0x04;
+const MASK_BACKGROUND: u8 = 0x08;
+const MASK_SPRITES: u8 = 0x10;
+const MASK_EMPHASIS: u8 = 0xE0;
+
+// PPUSTATUS bits
+const STATUS_OVERFLOW: u8 = 0x20;
+const STATUS_SPRITE_ZERO: u8 = 0x40;
+const STATUS_VBLANK: u8 = 0x80;
+
+// Sprite attribute bits
+const SPRITE_PALETTE: u8 = 0x03;
+const SPRITE_PRIORITY: u8 = 0x20;
+const SPRITE_FLIP_H: u8 = 0x40;
+const SPRITE_FLIP_V: u8 = 0x80;
+
+const SPRITES_PER_LINE: usize = 8;
+
+#[derive(Debug)]
+pub struct PPU {
+    pub mem: PpuMemory,
+    pub oam: Vec<u8>,
+    ctrl: u8,         // $2000 PPUCTRL
+    mask: u8,         // $2001 PPUMASK
+    status: u8,       // $2002 PPUSTATUS
+    oam_address: u8,  // $2003 OAMADDR
+    buffer: u8,       // $2007 PPUDATA read buffer
+    register: u8,     // last value written to any register (open bus)
+
+    region: Region,
+    cycle: u16,       // 0-340
+    scanline: u16,    // 0-239 visible, last line of the frame is pre-render
+    frame: u64,       // frame counter
+    frame_complete: bool,
+
+    // NMI is asserted while both vblank flag and PPUCTRL bit 7 are set,
+    // and the CPU reacts to its rising edge
+    nmi_previous: bool,
+    nmi_delay: u8,
+    suppress_vblank: bool,
+
+    // Internal registers shared by scrolling and PPUADDR, "loopy" layout:
+    // yyy NN YYYYY XXXXX
+    // ||| || ||||| +++++-- coarse X scroll
+    // ||| || +++++-------- coarse Y scroll
+    // ||| ++-------------- nametable select
+    // +++----------------- fine Y scroll
+    v: u16,           // current VRAM address
+    t: u16,           // temporary VRAM address, top left onscreen tile
+    x: u8,            // fine X scroll
+    w: bool,          // first or second write toggle of $2005/$2006
+
+    // Background fetch latches
+    nametable_byte: u8,
+    attribute_byte: u8,
+    pattern_low: u8,
+    pattern_high: u8,
+
+    // Background shift registers
+    shift_pattern_low: u16,
+    shift_pattern_high: u16,
+    shift_attribute_low: u16,
+    shift_attribute_high: u16,
+
+    // Sprites found for the next scanline during evaluation
+    secondary_oam: Vec<u8>,
+    sprite_zero_next: bool,
+    sprite_count_next: usize,
+
+    // Sprites rendered on the current scanline
+    sprite_zero: bool,
+    sprite_count: usize,
+    sprite_x: [u8; SPRITES_PER_LINE],
+    sprite_attribute: [u8; SPRITES_PER_LINE],
+    sprite_pattern_low: [u8; SPRITES_PER_LINE],
+    sprite_pattern_high: [u8; SPRITES_PER_LINE],
+
+    back: Vec<u16>,   // frame being rendered
+    front: Vec<u16>,  // last complete frame
+}
+
+impl PPU {
+    pub fn new(mapper: Rc<RefCell<Box<dyn Mapper>>>) -> PPU {
+        PPU {
+            mem: PpuMemory::new(mapper),
+            oam: vec![0; 256],
+            ctrl: 0,
+            mask: 0,
+            status: 0,
+            oam_address: 0,
+            buffer: 0,
+            register: 0,
+            region: Region::default(),
+            cycle: 0,
+            scanline: 0,
+            frame: 0,
+            frame_complete: false,
+            nmi_previous: false,
+            nmi_delay: 0,
+            suppress_vblank: false,
+            v: 0,
+            t: 0,
+            x: 0,
+            w: false,
+            nametable_byte: 0,
+            attribute_byte: 0,
+            pattern_low: 0,
+            pattern_high: 0,
+            shift_pattern_low: 0,
+            shift_pattern_high: 0,
+            shift_attribute_low: 0,
+            shift_attribute_high: 0,
+            secondary_oam: vec![0xFF; SPRITES_PER_LINE * 4],
+            sprite_zero_next: false,
+            sprite_count_next: 0,
+            sprite_zero: false,
+            sprite_count: 0,
+            sprite_x: [0; SPRITES_PER_LINE],
+            sprite_attribute: [0; SPRITES_PER_LINE],
+            sprite_pattern_low: [0; SPRITES_PER_LINE],
+            sprite_pattern_high: [0; SPRITES_PER_LINE],
+            back: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            front: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.write_ctrl(0);
+        self.write_mask(0);
+        self.t = 0;
+        self.x = 0;
+        self.w = false;
+        self.buffer = 0;
+        self.cycle = 0;
+        self.scanline = 0;
+        self.frame = 0;
+        self.nmi_delay = 0;
+    }
+
+    // Last complete frame, each pixel is a 6-bit color index
+    // with PPUMASK emphasis bits in bits 6-8
+    pub fn frame(&self) -> &[u16] {
+        &self.front
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    pub fn get_cycle(&self) -> u16 {
+        self.cycle
+    }
+
+    pub fn get_scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    // Returns true once after each completed frame
+    pub fn take_frame_complete(&mut self) -> bool {
+        let complete = self.frame_complete;
+        self.frame_complete = false;
+        complete
+    }
+
+    pub fn read_register(&mut self, address: u16) -> u8 {
+        match address {
+            0x2002 => self.read_status(),
+            0x2004 => self.read_oam_data(),
+            0x2007 => self.read_data(),
+            // Write-only registers return the contents of the internal latch
+            0x2000..=0x2007 => self.register,
+            _ => 0,
+        }
+    }
+
+    pub fn write_register(&mut self, address: u16, value: u8) {
+        self.register = value;
+        match address {
+            0x2000 => self.write_ctrl(value),
+            0x2001 => self.write_mask(value),
+            0x2003 => self.write_oam_address(value),
+            0x2004 => self.write_oam_data(value),
+            0x2005 => self.write_scroll(value),
+            0x2006 => self.write_address(value),
+            0x2007 => self.write_data(value),
+            _ => {}
+        }
+    }
+
+    // Advances the PPU by one dot, returns true when NMI should be triggered
+    pub fn step(&mut self) -> bool {
+        let mut trigger_nmi = false;
+        if self.nmi_delay > 0 {
+            self.nmi_delay -= 1;
+            trigger_nmi = self.nmi_delay == 0 && self.nmi_line();
+        }
+
+        let rendering = self.rendering_enabled();
+        let visible_line = self.scanline < SCREEN_HEIGHT as u16;
+        let pre_render_line = self.scanline == self.region.pre_render_line();
+        let render_line = visible_line || pre_render_line;
+        let fetch_cycle = (self.cycle >= 1 && self.cycle <= 256)
+            || (self.cycle >= 321 && self.cycle <= 336);
+
+        if visible_line && self.cycle >= 1 && self.cycle <= 256 {
+            self.render_pixel();
+        }
+
+        if rendering && render_line {
+            if fetch_cycle {
+                self.shift_background();
+                match self.cycle % 8 {
+                    1 => self.fetch_nametable_byte(),
+                    3 => self.fetch_attribute_byte(),
+                    5 => self.fetch_pattern_low(),
+                    7 => self.fetch_pattern_high(),
+                    0 => {
+                        self.load_background();
+                        self.increment_x();
+                    }
+                    _ => {}
+                }
+            }
+
+            if self.cycle == 256 {
+                self.increment_y();
+            }
+
+            if self.cycle == 257 {
+                self.copy_x();
+            }
+
+            if pre_render_line && self.cycle >= 280 && self.cycle <= 304 {
+                self.copy_y();
+            }
+
+            // Sprite evaluation for the next scanline happens while the
+            // background is fetched, sprite patterns are fetched in 257-320
+            if self.cycle == 257 {
+                if visible_line {
+                    self.evaluate_sprites();
+                } else {
+                    self.sprite_count_next = 0;
+                    self.sprite_zero_next = false;
+                }
+                self.sprite_count = 0;
+            }
+
+            if self.cycle >= 257 && self.cycle <= 320 {
+                let slot = ((self.cycle - 257) / 8) as usize;
+                match self.cycle % 8 {
+                    5 => self.fetch_sprite_pattern(slot, 0),
+                    7 => self.fetch_sprite_pattern(slot, 8),
+                    _ => {}
+                }
+            }
+
+            if self.cycle == 320 {
+                self.sprite_count = self.sprite_count_next;
+                self.sprite_zero = self.sprite_zero_next;
+            }
+        }
+
+        if self.scanline == self.region.vblank_line() && self.cycle == 1 {
+            // Reading PPUSTATUS one dot earlier keeps the flag from being set
+            if !self.suppress_vblank {
+                self.status |= STATUS_VBLANK;
+                self.nmi_change();
+            }
+            self.suppress_vblank = false;
+            self.swap_frame();
+        }
+
+        if pre_render_line && self.cycle == 1 {
+            self.status &= !(STATUS_VBLANK | STATUS_SPRITE_ZERO | STATUS_OVERFLOW);
+            self.nmi_change();
+        }
+
+        self.tick(rendering);
+        trigger_nmi
+    }
+
+    // Moves to the next dot, skipping the last dot of the pre-render line
+    // on odd NTSC frames while rendering is enabled
+    fn tick(&mut self, rendering: bool) {
+        if rendering
+            && self.region.skips_odd_dot()
+            && self.frame % 2 == 1
+            && self.scanline == self.region.pre_render_line()
+            && self.cycle == DOTS_PER_SCANLINE - 2
+        {
+            self.cycle += 1;
+        }
+
+        self.cycle += 1;
+        if self.cycle == DOTS_PER_SCANLINE {
+            self.cycle = 0;
+            self.scanline += 1;
+            if self.scanline > self.region.pre_render_line() {
+                self.scanline = 0;
+                self.frame += 1;
+            }
+        }
+    }
+
+    fn nmi_line(&self) -> bool {
+        self.ctrl & CTRL_NMI != 0 && self.status & STATUS_VBLANK != 0
+    }
+
+    // Starts NMI delivery on rising edge of the NMI line
+    fn nmi_change(&mut self) {
+        let nmi = self.nmi_line();
+        if nmi && !self.nmi_previous {
+            self.nmi_delay = NMI_DELAY;
+        }
+        self.nmi_previous = nmi;
+    }
+
+    fn rendering_enabled(&self) -> bool {
+        self.mask & (MASK_BACKGROUND | MASK_SPRITES) != 0
+    }
+
+    fn swap_frame(&mut self) {
+        std::mem::swap(&mut self.front, &mut self.back);
+        self.frame_complete = true;
+    }
+
+    // Coarse X increment, wrapping into the horizontally adjacent nametable
+    fn increment_x(&mut self) {
+        if self.v & 0x001F == 31 {
+            self.v &= !0x001F;
+            self.v ^= 0x0400;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    // Fine Y increment, overflowing into coarse Y. Row 29 wraps into the
+    // vertically adjacent nametable, rows 30 and 31 (attribute data) wrap
+    // without switching nametables.
+    fn increment_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+        } else {
+            self.v &= !0x7000;
+            let mut y = (self.v & 0x03E0) >> 5;
+            if y == 29 {
+                y = 0;
+                self.v ^= 0x0800;
+            } else if y == 31 {
+                y = 0;
+            } else {
+                y += 1;
+            }
+            self.v = (self.v & !0x03E0) | (y << 5);
+        }
+    }
+
+    // Horizontal scroll bits from t to v
+    fn copy_x(&mut self) {
+        self.v = (self.v & 0xFBE0) | (self.t & 0x041F);
+    }
+
+    // Vertical scroll bits from t to v
+    fn copy_y(&mut self) {
+        self.v = (self.v & 0x841F) | (self.t & 0x7BE0);
+    }
+
+    fn fetch_nametable_byte(&mut self) {
+        let address = 0x2000 | (self.v & 0x0FFF);
+        self.nametable_byte = self.mem.read(address);
+    }
+
+    fn fetch_attribute_byte(&mut self) {
+        let v = self.v;
+        let address = 0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07);
+        let shift = ((v >> 4) & 0x04) | (v & 0x02);
+        self.attribute_byte = (self.mem.read(address) >> shift) & 0x03;
+    }
+
+    fn pattern_address(&self) -> u16 {
+        let table = if self.ctrl & CTRL_BACKGROUND_TABLE == 0 { 0 } else { 0x1000 };
+        let fine_y = (self.v >> 12) & 0x07;
+        table + self.nametable_byte as u16 * 16 + fine_y
+    }
+
+    fn fetch_pattern_low(&mut self) {
+        self.pattern_low = self.mem.read(self.pattern_address());
+    }
+
+    fn fetch_pattern_high(&mut self) {
+        self.pattern_high = self.mem.read(self.pattern_address() + 8);
+    }
+
+    // Loads fetched tile into the low byte of the shift registers
+    fn load_background(&mut self) {
+        self.shift_pattern_low = (self.shift_pattern_low & 0xFF00) | self.pattern_low as u16;
+        self.shift_pattern_high = (self.shift_pattern_high & 0xFF00) | self.pattern_high as u16;
+        let low = if self.attribute_byte & 1 != 0 { 0xFF } else { 0x00 };
+        let high = if self.attribute_byte & 2 != 0 { 0xFF } else { 0x00 };
+        self.shift_attribute_low = (self.shift_attribute_low & 0xFF00) | low;
+        self.shift_attribute_high = (self.shift_attribute_high & 0xFF00) | high;
+    }
+
+    fn shift_background(&mut self) {
+        self.shift_pattern_low <<= 1;
+        self.shift_pattern_high <<= 1;
+        self.shift_attribute_low <<= 1;
+        self.shift_attribute_high <<= 1;
+    }
+
+    // Background pixel at the current dot as a 4-bit palette index
+    fn background_pixel(&self) -> u8 {
+        let x = self.cycle - 1;
+        if self.mask & MASK_BACKGROUND == 0 {
+            return 0;
+        }
+        if x < 8 && self.mask & MASK_BACKGROUND_LEFT == 0 {
+            return 0;
+        }
+
+        let bit = 0x8000 >> self.x;
+        let pattern = ((self.shift_pattern_low & bit != 0) as u8)
+            | ((self.shift_pattern_high & bit != 0) as u8) << 1;
+        let attribute = ((self.shift_attribute_low & bit != 0) as u8)
+            | ((self.shift_attribute_high & bit != 0) as u8) << 1;
+        if pattern == 0 {
+            0
+        } else {
+            attribute << 2 | pattern
+        }
+    }
+
+    fn sprite_height(&self) -> u16 {
+        if self.ctrl & CTRL_SPRITE_SIZE == 0 { 8 } else { 16 }
+    }
+
+    // Finds up to eight sprites on the next scanline and copies them to
+    // the secondary OAM. Once it is full, the hardware keeps scanning for
+    // the overflow flag but increments both the sprite index and the byte
+    // offset within a sprite, so it compares wrong bytes as Y coordinates.
+    fn evaluate_sprites(&mut self) {
+        let height = self.sprite_height();
+        let in_range = |y: u8, scanline: u16| {
+            let row = scanline.wrapping_sub(y as u16);
+            row < height
+        };
+
+        for value in self.secondary_oam.iter_mut() {
+            *value = 0xFF;
+        }
+        self.sprite_count_next = 0;
+        self.sprite_zero_next = false;
+
+        let mut n = 0;
+        while n < 64 && self.sprite_count_next < SPRITES_PER_LINE {
+            let y = self.oam[n * 4];
+            if in_range(y, self.scanline) {
+                let slot = self.sprite_count_next * 4;
+                self.secondary_oam[slot..slot + 4].copy_from_slice(&self.oam[n * 4..n * 4 + 4]);
+                if n == 0 {
+                    self.sprite_zero_next = true;
+                }
+                self.sprite_count_next += 1;
+            }
+            n += 1;
+        }
+
+        let mut m = 0;
+        while n < 64 {
+            let y = self.oam[n * 4 + m];
+            if in_range(y, self.scanline) {
+                self.status |= STATUS_OVERFLOW;
+                break;
+            }
+            n += 1;
+            m = (m + 1) & 0x03;
+        }
+    }
+
+    // Fetches one pattern plane of a sprite slot, empty slots fetch tile $FF
+    fn fetch_sprite_pattern(&mut self, slot: usize, plane: u16) {
+        let height = self.sprite_height();
+        let (y, tile, attribute, x) = if slot < self.sprite_count_next {
+            let sprite = &self.secondary_oam[slot * 4..slot * 4 + 4];
+            (sprite[0], sprite[1], sprite[2], sprite[3])
+        } else {
+            (0xFF, 0xFF, 0xFF, 0xFF)
+        };
+
+        let mut row = self.scanline.wrapping_sub(y as u16) % height;
+        if attribute & SPRITE_FLIP_V != 0 {
+            row = height - 1 - row;
+        }
+
+        let address = if height == 8 {
+            let table = if self.ctrl & CTRL_SPRITE_TABLE == 0 { 0 } else { 0x1000 };
+            table + tile as u16 * 16 + row
+        } else {
+            let table = (tile as u16 & 0x01) * 0x1000;
+            let tile = (tile & 0xFE) as u16 + row / 8;
+            table + tile * 16 + row % 8
+        };
+
+        let mut pattern = self.mem.read(address + plane);
+        if slot >= self.sprite_count_next {
+            return;
+        }
+        if attribute & SPRITE_FLIP_H != 0 {
+            pattern = pattern.reverse_bits();
+        }
+        if plane == 0 {
+            self.sprite_pattern_low[slot] = pattern;
+        } else {
+            self.sprite_pattern_high[slot] = pattern;
+        }
+        self.sprite_attribute[slot] = attribute;
+        self.sprite_x[slot] = x;
+    }
+
+    // First opaque sprite pixel at the current dot as
+    // (slot, 4-bit palette index)
+    fn sprite_pixel(&self) -> (usize, u8) {
+        let x = self.cycle - 1;
+        if self.mask & MASK_SPRITES == 0 {
+            return (0, 0);
+        }
+        if x < 8 && self.mask & MASK_SPRITES_LEFT == 0 {
+            return (0, 0);
+        }
+
+        for slot in 0..self.sprite_count {
+            let offset = x.wrapping_sub(self.sprite_x[slot] as u16);
+            if offset >= 8 {
+                continue;
+            }
+            let bit = 0x80 >> offset;
+            let pattern = ((self.sprite_pattern_low[slot] & bit != 0) as u8)
+                | ((self.sprite_pattern_high[slot] & bit != 0) as u8) << 1;
+            if pattern != 0 {
+                let palette = self.sprite_attribute[slot] & SPRITE_PALETTE;
+                return (slot, palette << 2 | pattern);
+            }
+        }
+        (0, 0)
+    }
+
+    fn render_pixel(&mut self) {
+        let x = (self.cycle - 1) as usize;
+        let y = self.scanline as usize;
+        let pixel = if self.rendering_enabled() {
+            let background = self.background_pixel();
+            let (slot, sprite) = self.sprite_pixel();
+
+            if slot == 0 && self.sprite_zero && background != 0 && sprite != 0 && x != 255 {
+                self.status |= STATUS_SPRITE_ZERO;
+            }
+
+            match (background, sprite) {
+                (0, 0) => 0,
+                (0, _) => 0x10 | sprite,
+                (_, 0) => background,
+                _ if self.sprite_attribute[slot] & SPRITE_PRIORITY == 0 => 0x10 | sprite,
+                _ => background,
+            }
+        } else {
+            0
+        };
+        let mut color = self.mem.read(0x3F00 + pixel as u16);
+        if self.mask & MASK_GREYSCALE != 0 {
+            color &= 0x30;
+        }
+        let mut emphasis = (self.mask & MASK_EMPHASIS) as u16;
+        // 2C07 has red and green emphasis bits swapped
+        if self.region == Region::Pal {
+            emphasis = (emphasis & 0x80) | (emphasis & 0x20) << 1 | (emphasis & 0x40) >> 1;
+        }
+        self.back[y * SCREEN_WIDTH + x] = color as u16 | emphasis << 1;
+    }
+
+    // $2000: PPUCTRL
+    // Enabling NMI while in vblank triggers another NMI
+    fn write_ctrl(&mut self, value: u8) {
+        self.ctrl = value;
+        self.t = (self.t & 0xF3FF) | (((value & CTRL_NAMETABLE) as u16) << 10);
+        self.nmi_change();
+    }
+
+    // $2001: PPUMASK
+    fn write_mask(&mut self, value: u8) {
+        self.mask = value;
+    }
+
+    // $2002: PPUSTATUS
+    // Low five bits are stale bits of the latch. Reading clears the vblank
+    // flag and resets the $2005/$2006 write toggle.
+    fn read_status(&mut self) -> u8 {
+        if self.scanline == self.region.vblank_line() && self.cycle == 1 {
+            self.suppress_vblank = true;
+        }
+        let value = (self.status & 0xE0) | (self.register & 0x1F);
+        self.status &= !STATUS_VBLANK;
+        self.nmi_change();
+        self.w = false;
+        value
+    }
+
+    // $2003: OAMADDR
+    fn write_oam_address(&mut self, value: u8) {
+        self.oam_address = value;
+    }
+
+    // $2004: OAMDATA (read)
+    fn read_oam_data(&mut self) -> u8 {
+        let mut value = self.oam[self.oam_address as usize];
+        // Unimplemented bits of the sprite attribute byte read back as zero
+        if self.oam_address & 0x03 == 0x02 {
+            value &= 0xE3;
+        }
+        value
+    }
+
+    // $2004: OAMDATA (write)
+    fn write_oam_data(&mut self, value: u8) {
+        self.oam[self.oam_address as usize] = value;
+        self.oam_address = self.oam_address.wrapping_add(1);
+    }
+
+    // $2005: PPUSCROLL
+    // First write sets coarse and fine X, second write coarse and fine Y.
+    fn write_scroll(&mut self, value: u8) {
+        let value = value as u16;
+        if !self.w {
+            self.t = (self.t & 0xFFE0) | (value >> 3);
+            self.x = (value & 0x07) as u8;
+        } else {
+            self.t = (self.t & 0x8C1F) | ((value & 0x07) << 12) | ((value & 0xF8) << 2);
+        }
+        self.w = !self.w;
+    }
+
+    // $2006: PPUADDR
+    // Shares t with PPUSCROLL, the second write copies t to v, which lets
+    // games change vertical scroll mid-frame.
+    fn write_address(&mut self, value: u8) {
+        let value = value as u16;
+        if !self.w {
+            self.t = (self.t & 0x80FF) | ((value & 0x3F) << 8);
+        } else {
+            self.t = (self.t & 0xFF00) | value;
+            self.v = self.t;
+            self.mem.notify_address(self.v & 0x3FFF);
+        }
+        self.w = !self.w;
+    }
+
+    // $2007: PPUDATA (read)
+    // Reads below the palette are delayed by one through the internal buffer.
+    // Palette reads return immediately, while the buffer gets filled with the
+    // nametable byte "underneath" the palette.
+    fn read_data(&mut self) -> u8 {
+        let address = self.v & 0x3FFF;
+        let value = if address < 0x3F00 {
+            let buffered = self.buffer;
+            self.buffer = self.mem.read(address);
+            buffered
+        } else {
+            self.buffer = self.mem.read(address - 0x1000);
+            let color = self.mem.read(address);
+            if self.mask & MASK_GREYSCALE != 0 {
+                color & 0x30
+            } else {
+                color
+            }
+        };
+        self.increment_address();
+        value
+    }
+
+    // $2007: PPUDATA (write)
+    fn write_data(&mut self, value: u8) {
+        self.mem.write(self.v & 0x3FFF, value);
+        self.increment_address();
+    }
+
+    // VRAM address increment per PPUDATA access: 1 (across) or 32 (down).
+    // While rendering, the access instead triggers both coarse X and Y
+    // increments of the scroll logic.
+    fn increment_address(&mut self) {
+        let render_line = self.scanline < SCREEN_HEIGHT as u16
+            || self.scanline == self.region.pre_render_line();
+        if self.rendering_enabled() && render_line {
+            self.increment_x();
+            self.increment_y();
+        } else {
+            let step = if self.ctrl & CTRL_INCREMENT == 0 { 1 } else { 32 };
+            self.v = self.v.wrapping_add(step) & 0x7FFF;
+        }
+    }
+}
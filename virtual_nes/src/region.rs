@@ -0,0 +1,89 @@
+/// TV system of the console, affects CPU clock, PPU frame length and APU rates.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Region {
+    #[default]
+    Ntsc,
+    Pal,
+    // Famiclones: PAL frame with NTSC-like CPU to PPU ratio and APU
+    Dendy,
+}
+
+impl Region {
+    pub fn cpu_frequency(&self) -> i64 {
+        match self {
+            Region::Ntsc => 1_789_773,
+            Region::Pal => 1_662_607,
+            Region::Dendy => 1_773_448,
+        }
+    }
+
+    // PPU dots per CPU cycle as numerator and denominator
+    pub fn ppu_dots_ratio(&self) -> (usize, usize) {
+        match self {
+            Region::Ntsc => (3, 1),
+            Region::Pal => (16, 5),
+            Region::Dendy => (3, 1),
+        }
+    }
+
+    pub fn scanlines(&self) -> u16 {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal => 312,
+            Region::Dendy => 312,
+        }
+    }
+
+    // Scanline where the vblank flag is set
+    pub fn vblank_line(&self) -> u16 {
+        match self {
+            Region::Ntsc => 241,
+            Region::Pal => 241,
+            Region::Dendy => 291,
+        }
+    }
+
+    pub fn pre_render_line(&self) -> u16 {
+        self.scanlines() - 1
+    }
+
+    // Only NTSC PPU skips a dot on odd frames
+    pub fn skips_odd_dot(&self) -> bool {
+        *self == Region::Ntsc
+    }
+
+    // Noise channel timer periods in CPU cycles
+    pub fn noise_periods(&self) -> [u16; 16] {
+        match self {
+            Region::Pal => [
+                4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778,
+            ],
+            _ => [
+                4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+            ],
+        }
+    }
+
+    // DMC output rates in CPU cycles per bit
+    pub fn dmc_periods(&self) -> [u16; 16] {
+        match self {
+            Region::Pal => [
+                398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+            ],
+            _ => [
+                428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+            ],
+        }
+    }
+
+    // CPU cycles of APU frame counter steps. 4-step mode uses the first
+    // four and restarts on the next cycle, 5-step mode skips the fourth.
+    // Dendy uses NTSC APU.
+    pub fn frame_counter_steps(&self) -> [usize; 5] {
+        match self {
+            Region::Ntsc => [7457, 14913, 22371, 29829, 37281],
+            Region::Pal => [8313, 16627, 24939, 33253, 41565],
+            Region::Dendy => [7457, 14913, 22371, 29829, 37281],
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use super::region::Region;
 use byteorder::{LittleEndian, ReadBytesExt};
 use failure::Error;
 use std::io::{Read, Seek, SeekFrom};
@@ -6,6 +7,7 @@ const INES_MAGIC: u32 = 0x1a53454e;
 pub const PRG_BANK_SIZE: usize = 16 * 1024;
 const CHR_BANK_SIZE: usize = 8 * 1024;
 const PRG_RAM_SIZE: usize = 8 * 1024;
+const VRAM_SIZE: usize = 2 * 1024;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Mirroring {
@@ -19,10 +21,12 @@ pub enum Mirroring {
 #[derive(Debug)]
 pub struct Rom {
     pub mirroring: Mirroring,
+    pub region: Region,
     pub mapper: u8,
     pub prg: Vec<u8>,
     pub chr: Vec<u8>,
     pub ram: Vec<u8>,
+    pub vram: Vec<u8>, // extra nametable RAM of four-screen boards
 }
 
 impl Rom {
@@ -58,8 +62,22 @@ impl Rom {
             }
         };
 
+        let mut flags = [0; 7];
+        reader.read_exact(&mut flags)?;
+        let region = if flags7 & 0x0C == 0x08 {
+            // NES 2.0 CPU/PPU timing
+            match flags[3] & 0x03 {
+                1 => Region::Pal,
+                3 => Region::Dendy,
+                _ => Region::Ntsc,
+            }
+        } else if flags[0] & 0x01 != 0 {
+            Region::Pal
+        } else {
+            Region::Ntsc
+        };
+
         let has_trainer = flags6 & 0b100 != 0;
-        reader.seek(SeekFrom::Current(7))?;
         if has_trainer {
             reader.seek(SeekFrom::Current(512))?;
         }
@@ -75,12 +93,19 @@ impl Rom {
             chr = vec![0; 1 * CHR_BANK_SIZE];
         }
 
+        let vram = match mirroring {
+            Mirroring::FourScreen => vec![0; VRAM_SIZE],
+            _ => Vec::new(),
+        };
+
         Ok(Rom {
             mirroring: mirroring,
+            region: region,
             mapper: mapper,
             prg: prg,
             chr: chr,
             ram: ram,
+            vram: vram,
         })
     }
 }
@@ -88,6 +113,7 @@ impl Rom {
 #[cfg(test)]
 mod tests {
     use super::super::tests::create_rom;
+    use super::super::region::Region;
     use super::{Mirroring, PRG_RAM_SIZE};
 
     #[test]
@@ -97,6 +123,7 @@ mod tests {
         //println!("{:?}", rom);
         assert_eq!(rom.mapper, 1);
         assert_eq!(rom.mirroring, Mirroring::Vertical);
+        assert_eq!(rom.region, Region::Ntsc);
         assert_eq!(rom.ram.len(), PRG_RAM_SIZE);
     }
 }
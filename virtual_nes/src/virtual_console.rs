@@ -1,36 +1,58 @@
-use super::apu::APU;
+use super::apu::{Channel, OutputFilter, APU};
 use super::controller::Controller;
-use super::cpu::CpuMemory;
-use super::cpu::CPU;
+use super::cpu::{CpuMemory, StopReason, CPU};
 use super::mapper::create_mapper;
-use super::ppu::PPU;
+use super::ppu::{Palette, PPU};
+use super::region::Region;
 use super::rom::Rom;
+use super::wav::{to_i16, write_wav, BitDepth};
 use failure::Error;
 use std::cell::RefCell;
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 use std::rc::Rc;
 
 #[derive(Debug)]
 pub struct VirtualConsole {
-    cpu: CPU,
+    cpu: CPU<CpuMemory>,
+    palette: Palette,
+    region: Region,
 }
 
-const CPU_FREQUENCY: i64 = 1_789_773;
-
 impl VirtualConsole {
     pub fn new<T: Read + Seek>(reader: &mut T) -> Result<VirtualConsole, Error> {
         let rom = Rom::load(reader)?;
+        let region = rom.region;
         let mapper = Rc::new(RefCell::new(create_mapper(rom)?));
         let ppu = PPU::new(Rc::clone(&mapper));
-        let apu = APU::new();
+        let apu = APU::new(Rc::clone(&mapper));
         let player_1 = Controller::new();
         let player_2 = Controller::new();
         let cpu_mem = CpuMemory::new(mapper, ppu, apu, player_1, player_2);
         let cpu = CPU::new(cpu_mem);
 
-        Ok(VirtualConsole {
+        let mut console = VirtualConsole {
             cpu: cpu,
-        })
+            palette: Palette::default(),
+            region: region,
+        };
+        console.set_region(region);
+        Ok(console)
+    }
+
+    // Overrides the region detected from ROM header
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.cpu.mem.set_region(region);
+    }
+
+    pub fn get_region(&self) -> Region {
+        self.region
+    }
+
+    // Debugging aid: access to unmapped addresses stops the CPU with
+    // a bus fault instead of reading open bus or being ignored
+    pub fn set_bus_faults(&mut self, enabled: bool) {
+        self.cpu.mem.bus_faults = enabled;
     }
 
     pub fn reset(&mut self) {
@@ -38,22 +60,224 @@ impl VirtualConsole {
         self.cpu.reset();
     }
 
-    pub fn step(&mut self) -> usize {
-        let cpu_cycles = self.cpu.step();
-        let ppu_cycles = cpu_cycles * 3;
-        for _ in 0..ppu_cycles {
-            let trigger_nmi = self.cpu.mem.ppu.step();
-            if trigger_nmi {
-                self.cpu.trigger_nmi();
+    // Runs a CPU instruction, the rest of the console advances with
+    // every bus cycle of it. Nothing runs once the CPU has stopped.
+    pub fn step(&mut self) -> Result<usize, StopReason> {
+        self.cpu.step()
+    }
+
+    // Runs until the PPU completes a frame
+    pub fn step_frame(&mut self) -> Result<(), StopReason> {
+        loop {
+            self.step()?;
+            if self.cpu.mem.ppu.take_frame_complete() {
+                return Ok(());
             }
         }
-        cpu_cycles
     }
 
-    pub fn step_seconds(&mut self, seconds: i64) {
-        let mut cycles = CPU_FREQUENCY * seconds / 1000;
+    // Set when CPU has stopped, cleared by reset
+    pub fn get_stop_reason(&self) -> Option<StopReason> {
+        self.cpu.get_stop_reason()
+    }
+
+    // Last complete frame as 256x240 palette indices with emphasis bits
+    pub fn frame(&self) -> &[u16] {
+        self.cpu.mem.ppu.frame()
+    }
+
+    // Last complete frame converted to RGBA, buffer must hold 256x240x4 bytes
+    pub fn frame_rgba(&self, buffer: &mut [u8]) {
+        self.palette.to_rgba(self.frame(), buffer);
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    // Output rate of audio samples, 44100 by default
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.cpu.mem.apu.set_sample_rate(sample_rate);
+    }
+
+    pub fn get_sample_rate(&self) -> u32 {
+        self.cpu.mem.apu.get_sample_rate()
+    }
+
+    pub fn set_output_filter(&mut self, output_filter: OutputFilter) {
+        self.cpu.mem.apu.set_output_filter(output_filter);
+    }
+
+    pub fn get_output_filter(&self) -> OutputFilter {
+        self.cpu.mem.apu.get_output_filter()
+    }
+
+    // Mono audio generated since previous call, usually drained after
+    // every frame
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        let mut samples = Vec::new();
+        self.cpu.mem.apu.read_samples(&mut samples);
+        samples
+    }
+
+    pub fn take_samples_i16(&mut self) -> Vec<i16> {
+        self.take_samples().into_iter().map(to_i16).collect()
+    }
+
+    pub fn set_channel_gain(&mut self, channel: Channel, gain: f32) {
+        self.cpu.mem.apu.set_channel_gain(channel, gain);
+    }
+
+    pub fn get_channel_gain(&self, channel: Channel) -> f32 {
+        self.cpu.mem.apu.get_channel_gain(channel)
+    }
+
+    pub fn set_channel_muted(&mut self, channel: Channel, muted: bool) {
+        self.cpu.mem.apu.set_channel_muted(channel, muted);
+    }
+
+    pub fn set_channel_solo(&mut self, channel: Channel, solo: bool) {
+        self.cpu.mem.apu.set_channel_solo(channel, solo);
+    }
+
+    // Enables per channel streams for take_channel_samples
+    pub fn set_channel_taps(&mut self, enabled: bool) {
+        self.cpu.mem.apu.set_channel_taps(enabled);
+    }
+
+    // Audio of a single channel generated since previous call,
+    // unaffected by gain, mute and solo
+    pub fn take_channel_samples(&mut self, channel: Channel) -> Vec<f32> {
+        let mut samples = Vec::new();
+        self.cpu.mem.apu.read_channel_samples(channel, &mut samples);
+        samples
+    }
+
+    pub fn take_channel_samples_i16(&mut self, channel: Channel) -> Vec<i16> {
+        self.take_channel_samples(channel).into_iter().map(to_i16).collect()
+    }
+
+    // Records APU register writes until stop_vgm_log
+    pub fn start_vgm_log(&mut self) {
+        self.cpu.mem.apu.start_vgm_log();
+    }
+
+    // Writes VGM file with APU writes since start_vgm_log
+    pub fn stop_vgm_log(&mut self, writer: &mut dyn Write) -> Result<(), Error> {
+        match self.cpu.mem.apu.stop_vgm_log() {
+            Some(vgm) => Ok(writer.write_all(&vgm)?),
+            None => Err(format_err!("VGM logging was not started")),
+        }
+    }
+
+    // Runs given number of frames and writes audio as WAV at the sample
+    // rate, with optional stems of individual channels
+    pub fn record_wav(
+        &mut self,
+        frames: usize,
+        sample_rate: u32,
+        bit_depth: BitDepth,
+        mix: &mut dyn Write,
+        stems: &mut [(Channel, &mut dyn Write)],
+    ) -> Result<(), Error> {
+        let rate = self.get_sample_rate();
+        let taps = self.cpu.mem.apu.channel_taps_enabled();
+        self.set_sample_rate(sample_rate);
+        self.set_channel_taps(taps || !stems.is_empty());
+
+        let channels: Vec<Channel> = stems.iter().map(|(channel, _)| *channel).collect();
+        let recorded = self.record_frames(frames, &channels);
+
+        // Settings are restored also when CPU has stopped
+        self.set_sample_rate(rate);
+        self.set_channel_taps(taps);
+        let (samples, stem_samples) = recorded?;
+
+        write_wav(mix, &samples, sample_rate, bit_depth)?;
+        for ((_, writer), samples) in stems.iter_mut().zip(stem_samples.iter()) {
+            write_wav(&mut **writer, samples, sample_rate, bit_depth)?;
+        }
+        Ok(())
+    }
+
+    // Mixed and per channel audio of given number of frames
+    fn record_frames(
+        &mut self,
+        frames: usize,
+        channels: &[Channel],
+    ) -> Result<(Vec<f32>, Vec<Vec<f32>>), StopReason> {
+        // Drop audio generated before recording
+        self.take_samples();
+        for channel in channels {
+            self.take_channel_samples(*channel);
+        }
+
+        let mut samples = Vec::new();
+        let mut channel_samples = vec![Vec::new(); channels.len()];
+        for _ in 0..frames {
+            self.step_frame()?;
+            self.cpu.mem.apu.read_samples(&mut samples);
+            for (channel, buffer) in channels.iter().zip(channel_samples.iter_mut()) {
+                self.cpu.mem.apu.read_channel_samples(*channel, buffer);
+            }
+        }
+        Ok((samples, channel_samples))
+    }
+
+    pub fn step_millis(&mut self, millis: i64) -> Result<(), StopReason> {
+        let mut cycles = self.region.cpu_frequency() * millis / 1000;
         while cycles > 0i64 {
-            cycles -= self.step() as i64;
+            cycles -= self.step()? as i64;
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::cpu::Bus;
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn record_wav() {
+        let mut file = File::open("../roms/nestest/nestest.nes").unwrap();
+        let mut console = VirtualConsole::new(&mut file).unwrap();
+        console.reset();
+
+        let mut mix = Vec::new();
+        let mut triangle = Vec::new();
+        console
+            .record_wav(
+                10,
+                48_000,
+                BitDepth::Bits16,
+                &mut mix,
+                &mut [(Channel::Triangle, &mut triangle)],
+            )
+            .unwrap();
+
+        // 10 NTSC frames are about 1/6 of a second
+        let samples = (mix.len() - 44) / 2;
+        assert!(samples > 7900 && samples < 8000);
+        assert_eq!(triangle.len(), mix.len());
+        assert_eq!(&mix[..4], b"RIFF");
+        assert_eq!(console.get_sample_rate(), 44_100);
+        assert!(!console.cpu.mem.apu.channel_taps_enabled());
+
+        // Jammed CPU fails the recording, settings are restored anyway
+        console.cpu.mem.write(0x0000, 0x02);
+        console.cpu.pc = 0x0000;
+        assert!(console
+            .record_wav(
+                1,
+                48_000,
+                BitDepth::Bits16,
+                &mut Vec::new(),
+                &mut [(Channel::Triangle, &mut Vec::new())],
+            )
+            .is_err());
+        assert_eq!(console.get_sample_rate(), 44_100);
+        assert!(!console.cpu.mem.apu.channel_taps_enabled());
     }
 }
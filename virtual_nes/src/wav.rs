@@ -0,0 +1,95 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+use failure::Error;
+use std::io::Write;
+
+/// Sample size of exported PCM WAV files.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitDepth {
+    Bits8,
+    Bits16,
+    Bits24,
+    Bits32,
+}
+
+impl BitDepth {
+    pub fn bytes(&self) -> u16 {
+        match self {
+            BitDepth::Bits8 => 1,
+            BitDepth::Bits16 => 2,
+            BitDepth::Bits24 => 3,
+            BitDepth::Bits32 => 4,
+        }
+    }
+}
+
+/// Writes mono samples in -1.0..1.0 range as PCM WAV.
+pub fn write_wav<W: Write + ?Sized>(
+    writer: &mut W,
+    samples: &[f32],
+    sample_rate: u32,
+    bit_depth: BitDepth,
+) -> Result<(), Error> {
+    let block_align = bit_depth.bytes();
+    let data_size = samples.len() as u32 * block_align as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_u32::<LittleEndian>(36 + data_size)?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_u32::<LittleEndian>(16)?;
+    writer.write_u16::<LittleEndian>(1)?; // PCM
+    writer.write_u16::<LittleEndian>(1)?; // channels
+    writer.write_u32::<LittleEndian>(sample_rate)?;
+    writer.write_u32::<LittleEndian>(sample_rate * block_align as u32)?;
+    writer.write_u16::<LittleEndian>(block_align)?;
+    writer.write_u16::<LittleEndian>(block_align * 8)?;
+
+    writer.write_all(b"data")?;
+    writer.write_u32::<LittleEndian>(data_size)?;
+    for sample in samples {
+        let sample = sample.clamp(-1.0, 1.0) as f64;
+        match bit_depth {
+            // 8-bit samples are unsigned
+            BitDepth::Bits8 => writer.write_u8((sample * 127.0 + 128.0) as u8)?,
+            BitDepth::Bits16 => writer.write_i16::<LittleEndian>(to_i16(sample as f32))?,
+            BitDepth::Bits24 => writer.write_i24::<LittleEndian>((sample * 8_388_607.0) as i32)?,
+            BitDepth::Bits32 => {
+                writer.write_i32::<LittleEndian>((sample * 2_147_483_647.0) as i32)?
+            }
+        }
+    }
+    Ok(())
+}
+
+// Sample in -1.0..1.0 range as signed 16-bit PCM
+pub(crate) fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) as f64 * 32_767.0) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header() {
+        let mut data = Vec::new();
+        write_wav(&mut data, &[0.0, 1.0, -1.0], 48_000, BitDepth::Bits16).unwrap();
+        assert_eq!(data.len(), 44 + 6);
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(&data[4..8], &[42, 0, 0, 0]);
+        assert_eq!(&data[24..28], &[0x80, 0xBB, 0x00, 0x00]);
+        assert_eq!(&data[34..36], &[16, 0]);
+        assert_eq!(&data[40..44], &[6, 0, 0, 0]);
+        assert_eq!(&data[44..], &[0x00, 0x00, 0xFF, 0x7F, 0x01, 0x80]);
+
+        let mut data = Vec::new();
+        write_wav(&mut data, &[0.0, 1.0], 44_100, BitDepth::Bits8).unwrap();
+        assert_eq!(&data[44..], &[0x80, 0xFF]);
+
+        let mut data = Vec::new();
+        write_wav(&mut data, &[-1.0], 44_100, BitDepth::Bits24).unwrap();
+        assert_eq!(&data[32..34], &[3, 0]);
+        assert_eq!(&data[44..], &[0x01, 0x00, 0x80]);
+    }
+}